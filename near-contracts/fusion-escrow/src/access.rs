@@ -0,0 +1,302 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near, require, AccountId};
+
+use crate::events::FusionEvent;
+use crate::{page, FusionEscrow, FusionEscrowExt};
+
+/// Operational roles. The owner implicitly holds every role; `Admin` implicitly
+/// holds the others and can grant or revoke them.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    ResolverManager,
+    /// Can pause methods; unpausing requires `Admin`
+    Guardian,
+}
+
+/// Whitelisted resolvers are either active or temporarily suspended.
+/// Revoked resolvers are removed from the map.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ResolverStatus {
+    Active,
+    Suspended,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverInfo {
+    pub account_id: AccountId,
+    pub status: ResolverStatus,
+}
+
+#[near]
+impl FusionEscrow {
+    // Ownership
+
+    /// Start a two-step ownership transfer; `None` cancels a pending transfer
+    pub fn propose_owner(&mut self, new_owner: Option<AccountId>) {
+        self.assert_owner();
+        self.pending_owner = new_owner;
+        FusionEvent::OwnershipTransferProposed {
+            owner: &self.owner,
+            pending_owner: self.pending_owner.as_ref(),
+        }
+        .emit();
+    }
+
+    /// Complete an ownership transfer; must be called by the proposed owner
+    pub fn accept_ownership(&mut self) {
+        let caller = env::predecessor_account_id();
+        require!(self.pending_owner.as_ref() == Some(&caller), "Only pending owner can accept");
+        let previous_owner = std::mem::replace(&mut self.owner, caller);
+        self.pending_owner = None;
+        FusionEvent::OwnershipTransferred {
+            previous_owner: &previous_owner,
+            new_owner: &self.owner,
+        }
+        .emit();
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner.clone()
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+
+    // Roles
+
+    pub fn grant_role(&mut self, account: AccountId, role: Role) {
+        self.assert_role(Role::Admin);
+        let mut roles = self.roles.get(&account).cloned().unwrap_or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+            self.roles.insert(account.clone(), roles);
+        }
+        FusionEvent::RoleGranted {
+            account: &account,
+            role,
+            by: &env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    pub fn revoke_role(&mut self, account: AccountId, role: Role) {
+        self.assert_role(Role::Admin);
+        let mut roles = self.roles.get(&account).cloned().unwrap_or_default();
+        roles.retain(|r| *r != role);
+        if roles.is_empty() {
+            self.roles.remove(&account);
+        } else {
+            self.roles.insert(account.clone(), roles);
+        }
+        FusionEvent::RoleRevoked {
+            account: &account,
+            role,
+            by: &env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    pub fn get_roles(&self, account: AccountId) -> Vec<Role> {
+        self.roles.get(&account).cloned().unwrap_or_default()
+    }
+
+    pub fn has_role(&self, account: AccountId, role: Role) -> bool {
+        self.account_has_role(&account, role)
+    }
+
+    // Resolvers
+
+    pub fn authorize_resolver(&mut self, resolver: AccountId) {
+        self.set_resolver_status(resolver, Some(ResolverStatus::Active));
+    }
+
+    /// Temporarily block a resolver from creating orders; existing orders are unaffected
+    pub fn suspend_resolver(&mut self, resolver: AccountId) {
+        require!(self.authorized_resolvers.contains_key(&resolver), "Resolver not registered");
+        self.set_resolver_status(resolver, Some(ResolverStatus::Suspended));
+    }
+
+    pub fn revoke_resolver(&mut self, resolver: AccountId) {
+        require!(self.authorized_resolvers.contains_key(&resolver), "Resolver not registered");
+        self.set_resolver_status(resolver, None);
+    }
+
+    /// Active resolvers with at least the minimum bond can create orders
+    pub fn is_authorized_resolver(&self, resolver: AccountId) -> bool {
+        self.authorized_resolvers.get(&resolver) == Some(&ResolverStatus::Active)
+            && self.has_min_bond(&resolver)
+    }
+
+    pub fn get_resolver_status(&self, resolver: AccountId) -> Option<ResolverStatus> {
+        self.authorized_resolvers.get(&resolver).copied()
+    }
+
+    pub fn get_resolvers_count(&self) -> u64 {
+        u64::from(self.authorized_resolvers.len())
+    }
+
+    /// Paginated list of registered resolvers, including suspended ones
+    pub fn get_resolvers(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<ResolverInfo> {
+        page(self.authorized_resolvers.iter(), self.authorized_resolvers.len(), from_index, limit)
+            .map(|(account_id, status)| ResolverInfo {
+                account_id: account_id.clone(),
+                status: *status,
+            })
+            .collect()
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn assert_owner(&self) {
+        require!(env::predecessor_account_id() == self.owner, "Only owner");
+    }
+
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.account_has_role(&env::predecessor_account_id(), role),
+            format!("Missing role: {:?}", role)
+        );
+    }
+
+    fn account_has_role(&self, account: &AccountId, role: Role) -> bool {
+        if *account == self.owner {
+            return true;
+        }
+        self.roles
+            .get(account)
+            .is_some_and(|roles| roles.contains(&role) || roles.contains(&Role::Admin))
+    }
+
+    fn set_resolver_status(&mut self, resolver: AccountId, status: Option<ResolverStatus>) {
+        self.assert_role(Role::ResolverManager);
+        match status {
+            Some(status) => self.authorized_resolvers.insert(resolver.clone(), status),
+            None => self.authorized_resolvers.remove(&resolver),
+        };
+        FusionEvent::ResolverStatusChanged {
+            resolver: &resolver,
+            status,
+            by: &env::predecessor_account_id(),
+        }
+        .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pause::PausableMethod;
+    use crate::test_utils::*;
+    use near_sdk::NearToken;
+
+    fn as_caller(account: AccountId) {
+        call(account, NearToken::from_yoctonear(0), 0);
+    }
+
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.propose_owner(Some(stranger()));
+        assert_eq!(contract.get_owner(), owner());
+        assert_eq!(contract.get_pending_owner(), Some(stranger()));
+
+        as_caller(stranger());
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner(), stranger());
+        assert_eq!(contract.get_pending_owner(), None);
+        assert!(!contract.has_role(owner(), Role::Admin));
+        assert!(contract.has_role(stranger(), Role::Admin));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only pending owner can accept")]
+    fn test_accept_ownership_requires_pending_owner() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.propose_owner(Some(stranger()));
+        contract.propose_owner(None);
+        as_caller(stranger());
+        contract.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner")]
+    fn test_propose_owner_requires_owner() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.grant_role(stranger(), Role::Admin);
+        as_caller(stranger());
+        contract.propose_owner(Some(stranger()));
+    }
+
+    #[test]
+    fn test_admin_implies_other_roles() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.grant_role(stranger(), Role::Admin);
+        assert!(contract.has_role(stranger(), Role::ResolverManager));
+        assert!(contract.has_role(stranger(), Role::Guardian));
+
+        contract.revoke_role(stranger(), Role::Admin);
+        assert!(contract.get_roles(stranger()).is_empty());
+        assert!(!contract.has_role(stranger(), Role::Guardian));
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role: Admin")]
+    fn test_guardian_cannot_unpause() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.grant_role(stranger(), Role::Guardian);
+        as_caller(stranger());
+        contract.pause(Some(vec![PausableMethod::CreateOrder]));
+        assert!(contract.is_paused(PausableMethod::CreateOrder));
+        contract.unpause(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role: ResolverManager")]
+    fn test_authorize_resolver_requires_role() {
+        let mut contract = setup();
+        as_caller(owner());
+        contract.grant_role(stranger(), Role::Guardian);
+        as_caller(stranger());
+        contract.authorize_resolver(stranger());
+    }
+
+    #[test]
+    fn test_resolver_lifecycle() {
+        let mut contract = setup();
+        assert_eq!(contract.get_resolver_status(owner()), None);
+        assert!(contract.is_authorized_resolver(resolver()));
+
+        as_caller(owner());
+        contract.grant_role(maker(), Role::ResolverManager);
+        as_caller(maker());
+        contract.authorize_resolver(stranger());
+        // Registered but without a bond
+        assert!(!contract.is_authorized_resolver(stranger()));
+
+        contract.suspend_resolver(resolver());
+        assert_eq!(contract.get_resolver_status(resolver()), Some(ResolverStatus::Suspended));
+        assert!(!contract.is_authorized_resolver(resolver()));
+        let page = contract.get_resolvers(Some(1), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].account_id, stranger());
+        assert_eq!(page[0].status, ResolverStatus::Active);
+
+        contract.revoke_resolver(resolver());
+        assert_eq!(contract.get_resolver_status(resolver()), None);
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(logs.iter().filter(|log| log.contains("\"event\":\"resolver_status_changed\"")).count(), 3);
+        assert!(logs.last().unwrap().contains("\"status\":null"));
+        assert_eq!(contract.get_resolvers_count(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{I128, U128};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near};
+
+use crate::{page, Asset, CrossChainOrder, FusionEscrow, FusionEscrowExt, SwapDirection};
+
+/// Running totals for one asset and direction
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetTotals {
+    pub locked: U128,             // Held for open orders, auctions and intents
+    pub completed_volume: U128,   // Cumulative amount of completed orders
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetTotalsView {
+    pub asset: Asset,
+    pub direction: SwapDirection,
+    #[serde(flatten)]
+    pub totals: AssetTotals,
+}
+
+/// Order counts by status. Completed and cancelled orders stay counted after pruning.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderCounts {
+    pub open: u64,
+    pub completed: u64,
+    pub cancelled: u64,
+}
+
+/// NEAR held by the contract compared with the NEAR it tracks as locked
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceCheck {
+    pub account_balance: U128,
+    pub storage_stake: U128,
+    pub locked_near: U128,             // NEAR locked for orders, auctions and intents
+    pub locked_safety_deposits: U128,
+    pub resolver_bonds: U128,          // Bonded and unbonding NEAR of all resolvers
+    pub storage_balances: U128,        // Prepaid storage not yet charged
+    pub surplus: I128,                 // Balance left after storage stake, locked funds and deposits
+    pub consistent: bool,
+}
+
+#[near]
+impl FusionEscrow {
+    /// Locked funds and completed volume per asset and direction
+    pub fn get_tvl(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AssetTotalsView> {
+        page(self.asset_totals.iter(), self.asset_totals.len(), from_index, limit)
+            .map(|((asset, direction), totals)| AssetTotalsView {
+                asset: asset.clone(),
+                direction: *direction,
+                totals: totals.clone(),
+            })
+            .collect()
+    }
+
+    pub fn get_asset_totals(&self, asset: Asset, direction: SwapDirection) -> AssetTotals {
+        self.asset_totals.get(&(asset, direction)).cloned().unwrap_or_default()
+    }
+
+    pub fn get_order_counts(&self) -> OrderCounts {
+        self.order_counts.clone()
+    }
+
+    /// Compare the account balance with everything the contract owes in NEAR: locked
+    /// funds, safety deposits, resolver bonds and prepaid storage balances. Token
+    /// balances cannot be read synchronously and are not checked.
+    pub fn check_balance_consistency(&self) -> BalanceCheck {
+        let account_balance = env::account_balance().as_yoctonear();
+        let storage_stake = env::storage_byte_cost().as_yoctonear() * u128::from(env::storage_usage());
+        let locked_near = [SwapDirection::EthToNear, SwapDirection::NearToEth]
+            .into_iter()
+            .map(|direction| self.get_asset_totals(Asset::Near, direction).locked.0)
+            .sum::<u128>();
+        let liabilities = storage_stake
+            + locked_near
+            + self.locked_safety_deposits
+            + self.total_bonds
+            + self.total_storage_balances;
+        let surplus = account_balance as i128 - liabilities as i128;
+
+        BalanceCheck {
+            account_balance: U128(account_balance),
+            storage_stake: U128(storage_stake),
+            locked_near: U128(locked_near),
+            locked_safety_deposits: U128(self.locked_safety_deposits),
+            resolver_bonds: U128(self.total_bonds),
+            storage_balances: U128(self.total_storage_balances),
+            surplus: I128(surplus),
+            consistent: surplus >= 0,
+        }
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn lock_funds(&mut self, asset: &Asset, direction: SwapDirection, amount: u128) {
+        let totals = self.asset_totals.entry((asset.clone(), direction)).or_default();
+        totals.locked = U128(totals.locked.0 + amount);
+    }
+
+    pub(crate) fn unlock_funds(&mut self, asset: &Asset, direction: SwapDirection, amount: u128) {
+        let totals = self.asset_totals.entry((asset.clone(), direction)).or_default();
+        totals.locked = U128(totals.locked.0.saturating_sub(amount));
+    }
+
+    pub(crate) fn track_order_created(&mut self, order: &CrossChainOrder) {
+        self.lock_funds(&order.asset, order.direction, order.amount.0);
+        self.locked_safety_deposits += order.safety_deposit.0;
+        self.order_counts.open += 1;
+    }
+
+    /// Release the funds of an order that was just completed or cancelled
+    pub(crate) fn track_order_settled(&mut self, order: &CrossChainOrder) {
+        self.unlock_funds(&order.asset, order.direction, order.amount.0);
+        self.locked_safety_deposits = self.locked_safety_deposits.saturating_sub(order.safety_deposit.0);
+        self.order_counts.open = self.order_counts.open.saturating_sub(1);
+        if order.completed {
+            let totals = self.asset_totals.entry((order.asset.clone(), order.direction)).or_default();
+            totals.completed_volume = U128(totals.completed_volume.0 + order.amount.0);
+            self.order_counts.completed += 1;
+        } else {
+            self.order_counts.cancelled += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::CANCELLATION_GRACE_PERIOD_NS;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    fn near(amount: u128) -> U128 {
+        U128(NearToken::from_near(amount).as_yoctonear())
+    }
+
+    #[test]
+    fn test_claim_moves_locked_to_completed_volume() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        let totals = contract.get_asset_totals(Asset::Near, SwapDirection::EthToNear);
+        assert_eq!(totals, AssetTotals { locked: near(2), completed_volume: U128(0) });
+        assert_eq!(contract.get_order_counts(), OrderCounts { open: 1, completed: 0, cancelled: 0 });
+
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        let totals = contract.get_asset_totals(Asset::Near, SwapDirection::EthToNear);
+        assert_eq!(totals, AssetTotals { locked: U128(0), completed_volume: near(2) });
+        assert_eq!(contract.get_order_counts(), OrderCounts { open: 0, completed: 1, cancelled: 0 });
+    }
+
+    #[test]
+    fn test_cancel_unlocks_without_volume() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        call(resolver(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * NS);
+        let _ = contract.cancel_order(order_hash(1));
+        let totals = contract.get_asset_totals(Asset::Near, SwapDirection::EthToNear);
+        assert_eq!(totals, AssetTotals::default());
+        assert_eq!(contract.get_order_counts(), OrderCounts { open: 0, completed: 0, cancelled: 1 });
+    }
+
+    #[test]
+    fn test_safety_deposit_tracked_until_settled() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(maker(), NearToken::from_near(2), 0);
+        contract.create_near_to_eth_order(
+            order_hash(1), resolver(), hashlock(1), None, DEADLINE_SECONDS, None, Some(U128(500)), None,
+        );
+        assert_eq!(contract.check_balance_consistency().locked_safety_deposits, U128(500));
+
+        call(resolver(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(contract.check_balance_consistency().locked_safety_deposits, U128(0));
+    }
+
+    #[test]
+    fn test_balance_check_counts_bonds_and_storage_balances() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        let check = contract.check_balance_consistency();
+        assert_eq!(check.resolver_bonds.0, contract.get_bond_config().min_bond.as_yoctonear());
+        assert_eq!(check.storage_balances, near(1));
+
+        // Exactly enough balance for the storage stake, the bond and the prepaid storage
+        let owed = check.storage_stake.0 + check.resolver_bonds.0 + check.storage_balances.0;
+        testing_env!(VMContextBuilder::new().account_balance(NearToken::from_yoctonear(owed)).build());
+        let check = contract.check_balance_consistency();
+        assert_eq!(check.surplus.0, 0);
+        assert!(check.consistent);
+
+        testing_env!(VMContextBuilder::new().account_balance(NearToken::from_yoctonear(owed - 1)).build());
+        assert!(!contract.check_balance_consistency().consistent);
+    }
+
+    #[test]
+    fn test_deposit_totals_follow_withdrawals_and_slashing() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        let storage_balance = contract.get_storage_balance(resolver()).0;
+        assert_eq!(contract.check_balance_consistency().storage_balances.0, storage_balance);
+
+        call(resolver(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.withdraw_storage(None);
+        assert_eq!(contract.check_balance_consistency().storage_balances, U128(0));
+
+        // An expired order slashes the bond, which leaves the total with the payout
+        call(stranger(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * NS + CANCELLATION_GRACE_PERIOD_NS + 1);
+        let _ = contract.cancel_order(order_hash(1));
+        let bonded = contract.get_bond(resolver()).unwrap().bonded;
+        assert!(bonded < contract.get_bond_config().min_bond);
+        assert_eq!(contract.check_balance_consistency().resolver_bonds.0, bonded.as_yoctonear());
+    }
+}
@@ -0,0 +1,317 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, require, AccountId, Gas, NearToken, Promise, PromiseOrValue, PromiseResult};
+
+use crate::access::Role;
+use crate::{page, EthereumOrder, FusionEscrow, FusionEscrowExt, IntentParams, OrderParams, SwapDirection};
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_FT_PAYOUT_CALLBACK: Gas = Gas::from_tgas(5);
+// Fees burnt for sending the `ft_transfer` and callback receipts of one payout
+const GAS_FOR_FT_PAYOUT_RECEIPTS: Gas = Gas::from_tgas(20);
+
+/// Asset locked in an order: native NEAR or a NEP-141 fungible token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(crate = "near_sdk::serde", tag = "type", rename_all = "snake_case")]
+pub enum Asset {
+    Near,
+    Ft { token_id: AccountId },
+}
+
+impl std::fmt::Display for Asset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Asset::Near => f.write_str("yoctoNEAR"),
+            Asset::Ft { token_id } => write!(f, "of {}", token_id),
+        }
+    }
+}
+
+/// `msg` payload of `ft_transfer_call` for funding an order with a NEP-141 token.
+/// The token sender is the resolver for eth_to_near and the maker for near_to_eth.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", tag = "action", rename_all = "snake_case")]
+pub enum FtOrderMsg {
+    CreateEthToNearOrder {
+        maker: AccountId,
+        #[serde(flatten)]
+        params: OrderParams,
+        ethereum_order: Option<EthereumOrder>,
+    },
+    CreateNearToEthOrder {
+        resolver: AccountId,
+        #[serde(flatten)]
+        params: OrderParams,
+        ethereum_order: Option<EthereumOrder>,
+    },
+    /// Publish an unassigned intent to the order book, the sender is the maker
+    PostIntent {
+        #[serde(flatten)]
+        params: IntentParams,
+    },
+}
+
+#[near]
+impl FusionEscrow {
+    /// NEP-141 receiver: creates an order funded with the transferred tokens. Only
+    /// accepted token contracts can call it. Storage is charged to the sender's
+    /// `deposit_storage` balance; orders with a safety deposit must be created with
+    /// a direct NEAR call. Any failure panics, so the token contract refunds the full amount.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        require!(self.accepted_tokens.contains(&token_id), "Token not accepted");
+        let asset = Asset::Ft { token_id };
+        let order_msg: FtOrderMsg = near_sdk::serde_json::from_str(&msg)
+            .unwrap_or_else(|e| env::panic_str(&format!("Invalid order msg: {}", e)));
+        let safety_deposit = match &order_msg {
+            FtOrderMsg::CreateEthToNearOrder { params, .. } | FtOrderMsg::CreateNearToEthOrder { params, .. } => {
+                params.safety_deposit
+            }
+            FtOrderMsg::PostIntent { params } => params.safety_deposit,
+        };
+        require!(
+            safety_deposit.is_none_or(|deposit| deposit.0 == 0),
+            "Safety deposit not supported for token transfers"
+        );
+
+        match order_msg {
+            FtOrderMsg::CreateEthToNearOrder { maker, params, ethereum_order } => self.create_order(
+                params, SwapDirection::EthToNear, maker, sender_id.clone(), asset, amount.0,
+                sender_id, None, ethereum_order, None,
+            ),
+            FtOrderMsg::CreateNearToEthOrder { resolver, params, ethereum_order } => self.create_order(
+                params, SwapDirection::NearToEth, sender_id.clone(), resolver, asset, amount.0,
+                sender_id, None, ethereum_order, None,
+            ),
+            FtOrderMsg::PostIntent { params } => {
+                self.create_intent(params, sender_id, asset, amount.0);
+            }
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Records a failed `ft_transfer` payout so the receiver can withdraw it later
+    #[private]
+    pub fn on_ft_payout(&mut self, receiver_id: AccountId, token_id: AccountId, amount: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        let key = (receiver_id, token_id);
+        let owed = self.failed_payouts.get(&key).copied().unwrap_or(0) + amount.0;
+        self.failed_payouts.insert(key.clone(), owed);
+        log!("ft_transfer of {} {} to {} failed, available via withdraw_failed_payout", amount.0, key.1, key.0);
+        false
+    }
+
+    /// Retry payouts whose `ft_transfer` failed (e.g. receiver was not storage-registered)
+    pub fn withdraw_failed_payout(&mut self, token_id: AccountId) -> Promise {
+        let receiver_id = env::predecessor_account_id();
+        let owed = self
+            .failed_payouts
+            .remove(&(receiver_id.clone(), token_id.clone()))
+            .expect("Nothing to withdraw");
+        self.transfer_asset(&Asset::Ft { token_id }, receiver_id, owed)
+    }
+
+    pub fn get_failed_payout(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128(self.failed_payouts.get(&(account_id, token_id)).copied().unwrap_or(0))
+    }
+
+    /// Allow `token_id` to fund orders through `ft_transfer_call`
+    pub fn add_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_role(Role::Admin);
+        self.accepted_tokens.insert(token_id.clone());
+        log!("Token {} accepted", token_id);
+    }
+
+    /// Stop accepting new orders in `token_id`; existing orders still settle
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_role(Role::Admin);
+        self.accepted_tokens.remove(&token_id);
+        log!("Token {} no longer accepted", token_id);
+    }
+
+    pub fn is_token_accepted(&self, token_id: AccountId) -> bool {
+        self.accepted_tokens.contains(&token_id)
+    }
+
+    pub fn get_accepted_tokens(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AccountId> {
+        page(self.accepted_tokens.iter(), self.accepted_tokens.len(), from_index, limit)
+            .cloned()
+            .collect()
+    }
+}
+
+impl FusionEscrow {
+    /// Gas that `payouts` calls of `transfer_asset` need: an `ft_transfer`, its
+    /// callback and their receipts per token payout. NEAR transfers fit in the
+    /// caller's own budget.
+    pub(crate) fn gas_for_payouts(asset: &Asset, payouts: u64) -> Gas {
+        match asset {
+            Asset::Near => Gas::from_gas(0),
+            Asset::Ft { .. } => {
+                let per_payout = GAS_FOR_FT_TRANSFER.as_gas()
+                    + GAS_FOR_FT_PAYOUT_CALLBACK.as_gas()
+                    + GAS_FOR_FT_PAYOUT_RECEIPTS.as_gas();
+                Gas::from_gas(per_payout * payouts)
+            }
+        }
+    }
+
+    /// Pay `amount` of `asset` to `receiver_id`. Token payouts are followed by a
+    /// callback that keeps failed transfers withdrawable.
+    pub(crate) fn transfer_asset(&self, asset: &Asset, receiver_id: AccountId, amount: u128) -> Promise {
+        match asset {
+            Asset::Near => Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount)),
+            Asset::Ft { token_id } => Promise::new(token_id.clone())
+                .function_call(
+                    "ft_transfer".to_string(),
+                    json!({ "receiver_id": receiver_id, "amount": U128(amount) })
+                        .to_string()
+                        .into_bytes(),
+                    NearToken::from_yoctonear(1),
+                    GAS_FOR_FT_TRANSFER,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_FT_PAYOUT_CALLBACK)
+                        .on_ft_payout(receiver_id, token_id.clone(), U128(amount)),
+                ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    /// Callback context of a payout whose `ft_transfer` had the given result
+    fn payout_result(result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new().predecessor_account_id(env::current_account_id()).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_ft_order_records_asset_and_pays_out_tokens() {
+        let mut contract = setup();
+        ft_order(&mut contract, 1, 1_000, DEADLINE_SECONDS);
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.asset, Asset::Ft { token_id: token() });
+        assert_eq!(order.amount, U128(1_000));
+        assert_eq!(order.storage_payer, resolver());
+
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(ft_transfers(), vec![(token(), maker(), 1_000)]);
+        assert!(transfers().is_empty());
+    }
+
+    #[test]
+    fn test_ft_cancel_refunds_tokens() {
+        let mut contract = setup();
+        ft_order(&mut contract, 1, 1_000, DEADLINE_SECONDS);
+        call(resolver(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * NS);
+        let _ = contract.cancel_order(order_hash(1));
+        assert_eq!(ft_transfers(), vec![(token(), resolver(), 1_000)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid order msg")]
+    fn test_ft_on_transfer_rejects_invalid_msg() {
+        let mut contract = setup();
+        call(token(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.ft_on_transfer(resolver(), U128(1_000), "{}".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token not accepted")]
+    fn test_ft_on_transfer_rejects_unlisted_token() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, resolver());
+        call(stranger(), NearToken::from_yoctonear(0), 0);
+        let msg = json!({
+            "action": "create_eth_to_near_order",
+            "maker": maker(),
+            "ethereum_order_hash": order_hash(1),
+            "hashlock": hashlock(1),
+            "deadline_seconds": DEADLINE_SECONDS,
+        });
+        let _ = contract.ft_on_transfer(resolver(), U128(1_000), msg.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token not accepted")]
+    fn test_removed_token_rejected() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.remove_accepted_token(token());
+        assert!(!contract.is_token_accepted(token()));
+        ft_order(&mut contract, 1, 1_000, DEADLINE_SECONDS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role: Admin")]
+    fn test_add_accepted_token_requires_admin() {
+        let mut contract = setup();
+        call(stranger(), NearToken::from_yoctonear(0), 0);
+        contract.add_accepted_token(stranger());
+    }
+
+    #[test]
+    #[should_panic(expected = "Safety deposit not supported for token transfers")]
+    fn test_ft_order_with_safety_deposit_rejected() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(token(), NearToken::from_yoctonear(0), 0);
+        let msg = json!({
+            "action": "create_near_to_eth_order",
+            "resolver": resolver(),
+            "ethereum_order_hash": order_hash(1),
+            "hashlock": hashlock(1),
+            "deadline_seconds": DEADLINE_SECONDS,
+            "safety_deposit": "100",
+        });
+        let _ = contract.ft_on_transfer(maker(), U128(1_000), msg.to_string());
+    }
+
+    #[test]
+    fn test_failed_payout_recorded_and_withdrawable() {
+        let mut contract = setup();
+        payout_result(PromiseResult::Successful(Vec::new()));
+        assert!(contract.on_ft_payout(maker(), token(), U128(1_000)));
+        assert_eq!(contract.get_failed_payout(maker(), token()), U128(0));
+
+        payout_result(PromiseResult::Failed);
+        assert!(!contract.on_ft_payout(maker(), token(), U128(1_000)));
+        payout_result(PromiseResult::Failed);
+        assert!(!contract.on_ft_payout(maker(), token(), U128(500)));
+        assert_eq!(contract.get_failed_payout(maker(), token()), U128(1_500));
+        assert_eq!(contract.get_failed_payout(stranger(), token()), U128(0));
+
+        call(maker(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.withdraw_failed_payout(token());
+        assert_eq!(ft_transfers(), vec![(token(), maker(), 1_500)]);
+        assert_eq!(contract.get_failed_payout(maker(), token()), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to withdraw")]
+    fn test_withdraw_without_failed_payout_rejected() {
+        let mut contract = setup();
+        call(maker(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.withdraw_failed_payout(token());
+    }
+}
@@ -0,0 +1,366 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId, NearToken, Promise};
+
+use crate::pause::PausableMethod;
+use crate::{
+    page, Asset, Bytes32, EthereumOrder, FusionEscrow, FusionEscrowExt, OrderParams, SwapDirection,
+    DEFAULT_COUNTERPARTY_CHAIN_ID, MAX_DEADLINE_SECONDS,
+};
+
+/// Fixed-point scale of auction rates: destination units per NEAR, times 10^18
+pub const RATE_PRECISION: u128 = 1_000_000_000_000_000_000;
+
+/// Maximum number of intermediate points on an auction curve
+pub const MAX_AUCTION_POINTS: usize = 8;
+
+/// Intermediate point of the price curve, `offset_seconds` after the auction start
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionPoint {
+    pub offset_seconds: u64,
+    pub rate: U128,
+}
+
+/// NEAR->ETH intent whose price decays from `start_rate` to `end_rate` until a
+/// resolver takes it. The curve is linear between consecutive points.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    pub maker: AccountId,
+    pub params: OrderParams,
+    pub locked: U128,                // Attached NEAR, funds the order once taken
+    pub start_rate: U128,
+    pub end_rate: U128,
+    pub auction_start: u64,          // Block timestamp in nanoseconds
+    pub duration: u64,               // Nanoseconds
+    pub points: Vec<AuctionPoint>,
+    pub storage_deposit: U128,       // Charged to the maker's storage balance, credited back on removal
+}
+
+impl DutchAuction {
+    /// Rate at `timestamp`: the start rate before the auction begins, the end rate
+    /// once it is over, interpolated between neighbouring points otherwise
+    pub fn rate_at(&self, timestamp: u64) -> u128 {
+        let elapsed = timestamp.saturating_sub(self.auction_start).min(self.duration);
+        let mut previous = (0u64, self.start_rate.0);
+        let points = self
+            .points
+            .iter()
+            .map(|point| (point.offset_seconds * 1_000_000_000, point.rate.0))
+            .chain(std::iter::once((self.duration, self.end_rate.0)));
+
+        for (offset, rate) in points {
+            if elapsed <= offset {
+                let (previous_offset, previous_rate) = previous;
+                let span = offset - previous_offset;
+                if span == 0 {
+                    return rate;
+                }
+                // Rates never increase along the curve, so this cannot underflow
+                return previous_rate - mul_div(previous_rate - rate, elapsed - previous_offset, span);
+            }
+            previous = (offset, rate);
+        }
+        self.end_rate.0
+    }
+
+    pub fn ends_at(&self) -> u64 {
+        self.auction_start + self.duration
+    }
+}
+
+/// `value * numerator / denominator` rounded down, for `numerator <= denominator`,
+/// without overflowing on rates near `u128::MAX`
+fn mul_div(value: u128, numerator: u64, denominator: u64) -> u128 {
+    let (numerator, denominator) = (u128::from(numerator), u128::from(denominator));
+    // Both remainders are below 2^64, so their product fits
+    value / denominator * numerator + value % denominator * numerator / denominator
+}
+
+#[near]
+impl FusionEscrow {
+    /// Lock the attached NEAR behind a Dutch auction instead of a fixed resolver.
+    /// The auction starts `start_delay_seconds` from now, immediately if omitted,
+    /// and runs for `duration_seconds`; point offsets are seconds from its start.
+    /// The auction storage, and the order storage and safety deposit once taken,
+    /// come from the maker's storage balance.
+    #[payable]
+    pub fn create_near_to_eth_auction(
+        &mut self,
+        params: OrderParams,
+        start_rate: U128,
+        end_rate: U128,
+        start_delay_seconds: Option<u64>,
+        duration_seconds: u64,
+        points: Option<Vec<AuctionPoint>>,
+    ) {
+        self.assert_not_paused(PausableMethod::Auction);
+        let maker = env::predecessor_account_id();
+        let locked = env::attached_deposit().as_yoctonear();
+        let points = points.unwrap_or_default();
+        let start_delay_seconds = start_delay_seconds.unwrap_or(0);
+
+        require!(locked > 0, "Attached deposit must be positive");
+        require!(locked >= self.min_order_amount(&Asset::Near), "Order amount below minimum");
+        require!(duration_seconds > 0, "Auction duration must be positive");
+        require!(
+            start_delay_seconds <= MAX_DEADLINE_SECONDS && duration_seconds <= MAX_DEADLINE_SECONDS,
+            format!("Auction times exceed maximum of {} seconds", MAX_DEADLINE_SECONDS)
+        );
+        let duration = duration_seconds * 1_000_000_000;
+        require!(start_rate.0 >= end_rate.0 && end_rate.0 > 0, "Invalid auction rates");
+        require!(points.len() <= MAX_AUCTION_POINTS, "Too many auction points");
+        let mut previous = (0u64, start_rate.0);
+        for point in &points {
+            let offset = point.offset_seconds;
+            require!(
+                offset > previous.0 && offset < duration_seconds,
+                "Auction points must be ordered within the duration"
+            );
+            require!(point.rate.0 <= previous.1 && point.rate.0 >= end_rate.0, "Auction rate must not increase");
+            previous = (offset, point.rate.0);
+        }
+
+        let ethereum_order_hash = params.ethereum_order_hash;
+        require!(!self.is_order_hash_taken(&ethereum_order_hash), "Order already exists");
+        require!(!params.hashlock.is_zero(), "Invalid hashlock");
+        self.assert_chain_enabled(params.counterparty_chain_id.unwrap_or(DEFAULT_COUNTERPARTY_CHAIN_ID));
+
+        let mut auction = DutchAuction {
+            maker,
+            params,
+            locked: U128(locked),
+            start_rate,
+            end_rate,
+            auction_start: env::block_timestamp() + start_delay_seconds * 1_000_000_000,
+            duration,
+            points,
+            storage_deposit: U128(0),
+        };
+
+        let initial_storage = env::storage_usage();
+        self.auctions.insert(ethereum_order_hash, auction.clone());
+        self.auctions.flush();
+        let storage_cost = Self::storage_cost_since(initial_storage);
+        self.charge_storage_balance(&auction.maker, storage_cost);
+        auction.storage_deposit = U128(storage_cost);
+        self.auctions.insert(ethereum_order_hash, auction.clone());
+        self.lock_funds(&Asset::Near, SwapDirection::NearToEth, auction.locked.0);
+
+        log!(
+            "NEAR->ETH auction created: {} yoctoNEAR for order {}, rate {} -> {}",
+            auction.locked.0, ethereum_order_hash, start_rate.0, end_rate.0
+        );
+    }
+
+    /// Take an auction at the current rate. The caller becomes the order resolver
+    /// and the order continues through the regular hashlocked flow, optionally
+    /// recording the counterparty order the resolver created on Ethereum.
+    pub fn take_auction(&mut self, ethereum_order_hash: Bytes32, ethereum_order: Option<EthereumOrder>) -> U128 {
+        self.assert_not_paused(PausableMethod::Auction);
+        let resolver = env::predecessor_account_id();
+        require!(self.is_authorized_resolver(resolver.clone()), "Not authorized resolver");
+
+        let auction = self.auctions.remove(&ethereum_order_hash).expect("Auction does not exist");
+        let now = env::block_timestamp();
+        require!(now >= auction.auction_start, "Auction not started");
+        require!(now <= auction.ends_at(), "Auction ended");
+
+        let rate = auction.rate_at(now);
+        self.unlock_funds(&Asset::Near, SwapDirection::NearToEth, auction.locked.0);
+        self.credit_storage_balance(&auction.maker, auction.storage_deposit.0);
+        self.create_order(
+            auction.params,
+            SwapDirection::NearToEth,
+            auction.maker.clone(),
+            resolver.clone(),
+            Asset::Near,
+            auction.locked.0,
+            auction.maker,
+            Some(U128(rate)),
+            ethereum_order,
+            None,
+        );
+
+        log!("Auction {} taken by {} at rate {}", ethereum_order_hash, resolver, rate);
+        U128(rate)
+    }
+
+    /// Withdraw an auction nobody took. The maker can cancel at any time before
+    /// it is taken.
+    pub fn cancel_auction(&mut self, ethereum_order_hash: Bytes32) -> Promise {
+        self.assert_not_migrating();
+        let auction = self.auctions.remove(&ethereum_order_hash).expect("Auction does not exist");
+        require!(env::predecessor_account_id() == auction.maker, "Only maker can cancel auction");
+        self.unlock_funds(&Asset::Near, SwapDirection::NearToEth, auction.locked.0);
+        self.credit_storage_balance(&auction.maker, auction.storage_deposit.0);
+
+        log!("Auction {} cancelled: Refunding {} yoctoNEAR to {}", ethereum_order_hash, auction.locked.0, auction.maker);
+        Promise::new(auction.maker).transfer(NearToken::from_yoctonear(auction.locked.0))
+    }
+
+    pub fn get_auction(&self, ethereum_order_hash: Bytes32) -> Option<DutchAuction> {
+        self.auctions.get(&ethereum_order_hash).cloned()
+    }
+
+    pub fn get_auctions(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<DutchAuction> {
+        page(self.auctions.values(), self.auctions.len(), from_index, limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Current rate of a pending auction
+    pub fn get_auction_rate(&self, ethereum_order_hash: Bytes32) -> U128 {
+        let auction = self.auctions.get(&ethereum_order_hash).expect("Auction does not exist");
+        U128(auction.rate_at(env::block_timestamp()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn auction(points: Vec<AuctionPoint>) -> DutchAuction {
+        DutchAuction {
+            maker: "maker.near".parse().unwrap(),
+            params: OrderParams {
+                ethereum_order_hash: Bytes32([1u8; 32]),
+                hashlock: Bytes32([1u8; 32]),
+                hash_algorithm: None,
+                deadline_seconds: 3600,
+                counterparty_chain_id: None,
+                safety_deposit: None,
+            },
+            locked: U128(1),
+            start_rate: U128(1_000),
+            end_rate: U128(500),
+            auction_start: 100 * SECOND,
+            duration: 100 * SECOND,
+            points,
+            storage_deposit: U128(0),
+        }
+    }
+
+    #[test]
+    fn test_linear_curve() {
+        let auction = auction(vec![]);
+        assert_eq!(auction.rate_at(0), 1_000);
+        assert_eq!(auction.rate_at(100 * SECOND), 1_000);
+        assert_eq!(auction.rate_at(150 * SECOND), 750);
+        assert_eq!(auction.rate_at(200 * SECOND), 500);
+        assert_eq!(auction.rate_at(300 * SECOND), 500);
+    }
+
+    #[test]
+    fn test_piecewise_curve() {
+        // Fast drop to 600 within the first 20s, then slow decay to 500
+        let auction = auction(vec![AuctionPoint { offset_seconds: 20, rate: U128(600) }]);
+        assert_eq!(auction.rate_at(110 * SECOND), 800);
+        assert_eq!(auction.rate_at(120 * SECOND), 600);
+        assert_eq!(auction.rate_at(160 * SECOND), 550);
+        assert_eq!(auction.rate_at(200 * SECOND), 500);
+    }
+
+    /// Maker auction for order 1 over 2 NEAR, decaying from 1000 to 500 over 100 seconds
+    fn create_auction(contract: &mut FusionEscrow, start_delay_seconds: Option<u64>) {
+        prepay_storage(contract, maker());
+        call(maker(), NearToken::from_near(2), 0);
+        let params = OrderParams {
+            ethereum_order_hash: order_hash(1),
+            hashlock: hashlock(1),
+            hash_algorithm: None,
+            deadline_seconds: 3600,
+            counterparty_chain_id: None,
+            safety_deposit: None,
+        };
+        contract.create_near_to_eth_auction(params, U128(1_000), U128(500), start_delay_seconds, 100, None);
+    }
+
+    #[test]
+    fn test_curve_with_extreme_rates_does_not_overflow() {
+        let mut auction = auction(vec![]);
+        auction.start_rate = U128(u128::MAX);
+        auction.end_rate = U128(1);
+        auction.duration = crate::MAX_DEADLINE_SECONDS * SECOND;
+        assert_eq!(auction.rate_at(auction.auction_start + auction.duration / 2), 1 << 127);
+        assert_eq!(auction.rate_at(auction.ends_at()), 1);
+    }
+
+    #[test]
+    fn test_take_auction_creates_order_at_current_rate() {
+        let mut contract = setup();
+        create_auction(&mut contract, None);
+
+        call(resolver(), NearToken::from_yoctonear(0), 50 * SECOND);
+        assert_eq!(contract.take_auction(order_hash(1), None), U128(750));
+        assert!(contract.get_auction(order_hash(1)).is_none());
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.maker, maker());
+        assert_eq!(order.resolver, resolver());
+        assert_eq!(order.amount, U128(NearToken::from_near(2).as_yoctonear()));
+        assert_eq!(order.auction_rate, Some(U128(750)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Auction not started")]
+    fn test_take_auction_before_start_rejected() {
+        let mut contract = setup();
+        create_auction(&mut contract, Some(60));
+        call(resolver(), NearToken::from_yoctonear(0), 30 * SECOND);
+        contract.take_auction(order_hash(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Auction ended")]
+    fn test_take_auction_after_end_rejected() {
+        let mut contract = setup();
+        create_auction(&mut contract, None);
+        call(resolver(), NearToken::from_yoctonear(0), 101 * SECOND);
+        contract.take_auction(order_hash(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized resolver")]
+    fn test_take_auction_requires_authorized_resolver() {
+        let mut contract = setup();
+        create_auction(&mut contract, None);
+        call(stranger(), NearToken::from_yoctonear(0), 50 * SECOND);
+        contract.take_auction(order_hash(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be positive")]
+    fn test_auction_without_deposit_rejected() {
+        let mut contract = setup();
+        call(maker(), NearToken::from_yoctonear(0), 0);
+        contract.create_near_to_eth_auction(auction(vec![]).params, U128(1_000), U128(500), None, 100, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order amount below minimum")]
+    fn test_auction_below_minimum_rejected() {
+        let mut contract = setup();
+        call(maker(), NearToken::from_yoctonear(1), 0);
+        contract.create_near_to_eth_auction(auction(vec![]).params, U128(1_000), U128(500), None, 100, None);
+    }
+
+    #[test]
+    fn test_cancel_auction_credits_storage() {
+        let mut contract = setup();
+        create_auction(&mut contract, None);
+        let auction = contract.get_auction(order_hash(1)).unwrap();
+        assert_eq!(auction.locked.0, NearToken::from_near(2).as_yoctonear());
+        let prepaid = NearToken::from_near(1).as_yoctonear();
+        assert_eq!(contract.get_storage_balance(maker()).0, prepaid - auction.storage_deposit.0);
+
+        call(maker(), NearToken::from_yoctonear(0), SECOND);
+        let _ = contract.cancel_auction(order_hash(1));
+        assert_eq!(contract.get_storage_balance(maker()).0, prepaid);
+        assert_eq!(transfers(), vec![(maker(), NearToken::from_near(2).as_yoctonear())]);
+    }
+}
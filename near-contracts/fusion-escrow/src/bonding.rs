@@ -0,0 +1,259 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId, NearToken, Promise};
+
+use crate::access::Role;
+use crate::events::FusionEvent;
+use crate::pause::PausableMethod;
+use crate::{Bytes32, FusionEscrow, FusionEscrowExt};
+
+pub const DEFAULT_MIN_BOND: NearToken = NearToken::from_near(10);
+pub const DEFAULT_SLASH_BPS: u16 = 1_000;
+pub const DEFAULT_UNBONDING_DELAY_NS: u64 = 24 * 3600 * 1_000_000_000;
+
+/// Owner-managed bonding parameters
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BondConfig {
+    pub min_bond: NearToken,         // Bond required for a resolver to be active
+    pub slash_bps: u16,              // Share of the bond slashed per expired order
+    pub unbonding_delay_ns: u64,     // Delay between unbond request and withdrawal, also bounds ETH->NEAR deadlines
+}
+
+impl Default for BondConfig {
+    fn default() -> Self {
+        Self {
+            min_bond: DEFAULT_MIN_BOND,
+            slash_bps: DEFAULT_SLASH_BPS,
+            unbonding_delay_ns: DEFAULT_UNBONDING_DELAY_NS,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverBond {
+    pub bonded: NearToken,           // Active bond, counts towards `min_bond`
+    pub unbonding: NearToken,        // Requested for withdrawal, still slashable
+    pub unbonding_available_at: u64, // Timestamp after which `unbonding` can be withdrawn
+}
+
+impl ResolverBond {
+    pub(crate) fn total(&self) -> u128 {
+        self.bonded.as_yoctonear() + self.unbonding.as_yoctonear()
+    }
+}
+
+#[near]
+impl FusionEscrow {
+    /// Replace the bond config. Orders already open keep the deadline they were created
+    /// with, so lowering `unbonding_delay_ns` can let a bond be withdrawn before those
+    /// orders are publicly cancellable.
+    pub fn set_bond_config(&mut self, config: BondConfig) {
+        self.assert_role(Role::Admin);
+        require!(config.slash_bps <= 10_000, "Slash exceeds 100%");
+        self.bond_config = config;
+        log!("Bond config updated");
+    }
+
+    /// Add the attached NEAR to the caller's resolver bond
+    #[payable]
+    pub fn deposit_bond(&mut self) {
+        self.assert_not_paused(PausableMethod::DepositBond);
+        let resolver = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        require!(amount.as_yoctonear() > 0, "Must attach NEAR tokens");
+
+        let mut bond = self.bonds.get(&resolver).cloned().unwrap_or_default();
+        bond.bonded = bond.bonded.saturating_add(amount);
+        self.bonds.insert(resolver.clone(), bond.clone());
+        self.total_bonds += amount.as_yoctonear();
+
+        FusionEvent::BondDeposited { resolver: &resolver, amount, total: bond.bonded }.emit();
+    }
+
+    /// Move part of the active bond into unbonding. Requesting again adds to the
+    /// pending amount and restarts the delay.
+    pub fn request_unbond(&mut self, amount: NearToken) {
+        self.assert_not_migrating();
+        let resolver = env::predecessor_account_id();
+        let mut bond = self.bonds.get(&resolver).cloned().expect("No bond");
+        require!(amount.as_yoctonear() > 0 && amount <= bond.bonded, "Invalid unbond amount");
+
+        bond.bonded = bond.bonded.saturating_sub(amount);
+        bond.unbonding = bond.unbonding.saturating_add(amount);
+        bond.unbonding_available_at = env::block_timestamp() + self.bond_config.unbonding_delay_ns;
+        self.bonds.insert(resolver.clone(), bond.clone());
+
+        FusionEvent::UnbondRequested {
+            resolver: &resolver,
+            amount,
+            available_at: bond.unbonding_available_at,
+        }
+        .emit();
+    }
+
+    pub fn withdraw_unbonded(&mut self) -> Promise {
+        self.assert_not_migrating();
+        let resolver = env::predecessor_account_id();
+        let mut bond = self.bonds.get(&resolver).cloned().expect("No bond");
+        require!(bond.unbonding.as_yoctonear() > 0, "Nothing to withdraw");
+        require!(env::block_timestamp() >= bond.unbonding_available_at, "Unbonding delay not met");
+
+        let amount = bond.unbonding;
+        bond.unbonding = NearToken::from_yoctonear(0);
+        if bond.total() == 0 {
+            self.bonds.remove(&resolver);
+        } else {
+            self.bonds.insert(resolver.clone(), bond);
+        }
+        self.total_bonds = self.total_bonds.saturating_sub(amount.as_yoctonear());
+
+        FusionEvent::BondWithdrawn { resolver: &resolver, amount }.emit();
+        Promise::new(resolver).transfer(amount)
+    }
+
+    pub fn get_bond(&self, resolver: AccountId) -> Option<ResolverBond> {
+        self.bonds.get(&resolver).cloned()
+    }
+
+    pub fn get_bond_config(&self) -> BondConfig {
+        self.bond_config.clone()
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn has_min_bond(&self, resolver: &AccountId) -> bool {
+        let bonded = self.bonds.get(resolver).map_or(NearToken::from_yoctonear(0), |bond| bond.bonded);
+        bonded >= self.bond_config.min_bond
+    }
+
+    /// Slash the resolver's bond for an order that expired unclaimed and pay it to the maker.
+    /// The active bond is slashed first, then any amount still unbonding.
+    pub(crate) fn slash_resolver(&mut self, resolver: &AccountId, maker: &AccountId, ethereum_order_hash: &Bytes32) -> Option<Promise> {
+        let mut bond = self.bonds.get(resolver)?.clone();
+        let slash = bond.total() * self.bond_config.slash_bps as u128 / 10_000;
+        if slash == 0 {
+            return None;
+        }
+
+        let from_bonded = std::cmp::min(slash, bond.bonded.as_yoctonear());
+        bond.bonded = NearToken::from_yoctonear(bond.bonded.as_yoctonear() - from_bonded);
+        bond.unbonding = NearToken::from_yoctonear(bond.unbonding.as_yoctonear() - (slash - from_bonded));
+        self.bonds.insert(resolver.clone(), bond);
+        self.total_bonds = self.total_bonds.saturating_sub(slash);
+
+        let amount = NearToken::from_yoctonear(slash);
+        FusionEvent::ResolverSlashed {
+            resolver,
+            maker,
+            ethereum_order_hash,
+            amount,
+        }
+        .emit();
+        Some(Promise::new(maker.clone()).transfer(amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    fn near(amount: u128) -> NearToken {
+        NearToken::from_near(amount)
+    }
+
+    #[test]
+    fn test_slash_takes_bonded_then_unbonding() {
+        let mut contract = setup();
+        call(owner(), near(0), 0);
+        contract.set_bond_config(BondConfig { slash_bps: 5_000, ..BondConfig::default() });
+        near_order(&mut contract, 1, near(2), DEADLINE_SECONDS);
+        call(resolver(), near(0), 0);
+        contract.request_unbond(near(8));
+
+        call(resolver(), near(0), (DEADLINE_SECONDS + 1) * NS);
+        let _ = contract.cancel_order(order_hash(1));
+        // Half of the 10 NEAR bond: all 2 NEAR still bonded and 3 of the 8 unbonding
+        assert_eq!(transfers(), vec![
+            (resolver(), near(2).as_yoctonear()),
+            (maker(), near(5).as_yoctonear()),
+        ]);
+        let bond = contract.get_bond(resolver()).unwrap();
+        assert_eq!(bond.bonded, near(0));
+        assert_eq!(bond.unbonding, near(5));
+    }
+
+    #[test]
+    fn test_claimed_order_keeps_bond() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, near(2), DEADLINE_SECONDS);
+        call(maker(), near(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(contract.get_bond(resolver()).unwrap().bonded, contract.get_bond_config().min_bond);
+    }
+
+    #[test]
+    fn test_withdraw_unbonded_after_delay() {
+        let mut contract = setup();
+        call(resolver(), near(0), 0);
+        contract.request_unbond(near(4));
+        assert!(!contract.is_authorized_resolver(resolver()));
+
+        let available_at = contract.get_bond(resolver()).unwrap().unbonding_available_at;
+        assert_eq!(available_at, DEFAULT_UNBONDING_DELAY_NS);
+        call(resolver(), near(0), available_at);
+        let _ = contract.withdraw_unbonded();
+        assert_eq!(transfers(), vec![(resolver(), near(4).as_yoctonear())]);
+        let bond = contract.get_bond(resolver()).unwrap();
+        assert_eq!(bond.bonded, near(6));
+        assert_eq!(bond.unbonding, near(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unbonding delay not met")]
+    fn test_withdraw_unbonded_before_delay_rejected() {
+        let mut contract = setup();
+        call(resolver(), near(0), 0);
+        contract.request_unbond(near(4));
+        call(resolver(), near(0), DEFAULT_UNBONDING_DELAY_NS - 1);
+        let _ = contract.withdraw_unbonded();
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline exceeds unbonding delay")]
+    fn test_order_outliving_unbonding_delay_rejected() {
+        let mut contract = setup();
+        let max_deadline_seconds = (DEFAULT_UNBONDING_DELAY_NS - crate::CANCELLATION_GRACE_PERIOD_NS) / NS - 1;
+        near_order(&mut contract, 1, near(2), max_deadline_seconds);
+        // Expiring exactly at the unbonding delay would let the bond go in the same block
+        // the order becomes publicly cancellable
+        near_order(&mut contract, 2, near(2), max_deadline_seconds + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unbonding delay not met")]
+    fn test_unbond_requested_during_order_waits_for_its_expiry() {
+        let mut contract = setup();
+        let max_deadline_seconds = (DEFAULT_UNBONDING_DELAY_NS - crate::CANCELLATION_GRACE_PERIOD_NS) / NS - 1;
+        near_order(&mut contract, 1, near(2), max_deadline_seconds);
+        call(resolver(), near(0), 0);
+        contract.request_unbond(near(10));
+
+        // The whole bond stays slashable until the funder's grace period has passed
+        call(resolver(), near(0), max_deadline_seconds * NS + crate::CANCELLATION_GRACE_PERIOD_NS);
+        let _ = contract.withdraw_unbonded();
+    }
+
+    #[test]
+    #[should_panic(expected = "Not authorized resolver")]
+    fn test_order_requires_min_bond() {
+        let mut contract = setup();
+        call(resolver(), near(0), 0);
+        contract.request_unbond(near(1));
+        near_order(&mut contract, 1, near(2), DEADLINE_SECONDS);
+    }
+}
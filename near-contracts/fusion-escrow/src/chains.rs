@@ -0,0 +1,160 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{log, near, require};
+
+use crate::access::Role;
+use crate::{page, FusionEscrow, FusionEscrowExt};
+
+/// Ethereum mainnet, used when an order does not name its counterparty chain
+pub const DEFAULT_COUNTERPARTY_CHAIN_ID: u64 = 1;
+
+/// Direction of a swap relative to NEAR. The counterparty side is identified
+/// by the order's `counterparty_chain_id`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+#[borsh(use_discriminant = true)]
+pub enum SwapDirection {
+    EthToNear = 0,
+    NearToEth = 1,
+}
+
+/// Counterparty EVM chain accepted by the contract. `confirmations` is the number
+/// of blocks resolvers and watchers must wait for on that chain before acting.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub name: String,
+    pub confirmations: u32,
+    pub enabled: bool,
+}
+
+#[near]
+impl FusionEscrow {
+    /// Add or update a counterparty chain
+    pub fn register_chain(&mut self, chain: ChainInfo) {
+        self.assert_role(Role::Admin);
+        require!(!chain.name.is_empty(), "Chain name required");
+        log!("Registered chain {} ({})", chain.chain_id, chain.name);
+        self.chains.insert(chain.chain_id, chain);
+    }
+
+    /// Disabling a chain blocks new orders; existing orders can still settle
+    pub fn set_chain_enabled(&mut self, chain_id: u64, enabled: bool) {
+        self.assert_role(Role::Admin);
+        let chain = self.chains.get_mut(&chain_id).expect("Chain not registered");
+        chain.enabled = enabled;
+        log!("Chain {} enabled: {}", chain_id, enabled);
+    }
+
+    pub fn get_chain(&self, chain_id: u64) -> Option<ChainInfo> {
+        self.chains.get(&chain_id).cloned()
+    }
+
+    pub fn get_chains(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<ChainInfo> {
+        page(self.chains.values(), self.chains.len(), from_index, limit)
+            .cloned()
+            .collect()
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn assert_chain_enabled(&self, chain_id: u64) {
+        require!(
+            self.chains.get(&chain_id).is_some_and(|chain| chain.enabled),
+            "Counterparty chain not supported"
+        );
+    }
+
+    pub(crate) fn default_chains() -> Vec<ChainInfo> {
+        vec![ChainInfo {
+            chain_id: DEFAULT_COUNTERPARTY_CHAIN_ID,
+            name: "Ethereum".to_string(),
+            confirmations: 12,
+            enabled: true,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{serde_json, NearToken};
+
+    const BASE: u64 = 8453;
+
+    fn base() -> ChainInfo {
+        ChainInfo { chain_id: BASE, name: "Base".to_string(), confirmations: 64, enabled: true }
+    }
+
+    /// ETH->NEAR order `n` against the given counterparty chain, with storage prepaid
+    fn chain_order(contract: &mut FusionEscrow, n: u8, chain_id: u64) {
+        prepay_storage(contract, resolver());
+        call(resolver(), NearToken::from_near(1), 0);
+        contract.create_eth_to_near_order(order_hash(n), maker(), hashlock(n), None, 600, Some(chain_id), None, None);
+    }
+
+    #[test]
+    fn test_order_records_registered_chain() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.register_chain(base());
+        assert_eq!(contract.get_chains(None, None).len(), 2);
+        assert_eq!(contract.get_chain(BASE).unwrap().confirmations, 64);
+
+        chain_order(&mut contract, 1, BASE);
+        near_order(&mut contract, 2, NearToken::from_near(1), 600);
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.counterparty_chain_id, BASE);
+        assert_eq!(order.direction, SwapDirection::EthToNear);
+        assert_eq!(contract.get_order(order_hash(2)).unwrap().counterparty_chain_id, DEFAULT_COUNTERPARTY_CHAIN_ID);
+    }
+
+    #[test]
+    #[should_panic(expected = "Counterparty chain not supported")]
+    fn test_unregistered_chain_rejected() {
+        let mut contract = setup();
+        chain_order(&mut contract, 1, BASE);
+    }
+
+    #[test]
+    fn test_disabled_chain_settles_existing_orders() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.register_chain(base());
+        chain_order(&mut contract, 1, BASE);
+
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.set_chain_enabled(BASE, false);
+        assert!(!contract.get_chain(BASE).unwrap().enabled);
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert!(contract.get_order(order_hash(1)).unwrap().completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Counterparty chain not supported")]
+    fn test_disabled_chain_rejects_orders() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.register_chain(ChainInfo { enabled: false, ..base() });
+        chain_order(&mut contract, 1, BASE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role: Admin")]
+    fn test_register_chain_requires_admin() {
+        let mut contract = setup();
+        call(stranger(), NearToken::from_yoctonear(0), 0);
+        contract.register_chain(base());
+    }
+
+    #[test]
+    fn test_swap_direction_encoding() {
+        assert_eq!(serde_json::to_string(&SwapDirection::NearToEth).unwrap(), "\"near_to_eth\"");
+        let direction: SwapDirection = serde_json::from_str("\"eth_to_near\"").unwrap();
+        assert_eq!(direction, SwapDirection::EthToNear);
+        assert_eq!(near_sdk::borsh::to_vec(&SwapDirection::NearToEth).unwrap(), vec![1]);
+    }
+}
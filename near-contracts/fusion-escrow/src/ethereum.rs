@@ -0,0 +1,77 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::require;
+
+/// 20-byte Ethereum address. JSON accepts a hex string (optionally 0x-prefixed,
+/// any casing) and always serializes as lowercase 0x-prefixed hex.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EthAddress(pub [u8; 20]);
+
+impl EthAddress {
+    /// Parse a 40-character hex string, with or without 0x prefix
+    pub fn from_hex(value: &str) -> Result<Self, String> {
+        let stripped = value.strip_prefix("0x").unwrap_or(value);
+        if stripped.len() != 40 {
+            return Err(format!("Expected 20 bytes of hex, got {} characters", stripped.len()));
+        }
+        let bytes = hex::decode(stripped).map_err(|e| format!("Invalid hex: {}", e))?;
+        Ok(Self(bytes.try_into().expect("40 hex characters decode to 20 bytes")))
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+impl std::fmt::Display for EthAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl Serialize for EthAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for EthAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <String as Deserialize>::deserialize(deserializer)?;
+        EthAddress::from_hex(&value).map_err(de::Error::custom)
+    }
+}
+
+/// Counterparty order on Ethereum, as emitted in `CrossChainSwapInitiated` by
+/// `FusionNearExtension.sol`. Recorded so watchers can check that both legs match.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EthereumOrder {
+    pub maker: EthAddress,
+    pub taker: EthAddress,
+    pub token: EthAddress,
+    pub amount: U128,      // Token amount in its smallest unit (uint256 capped to u128)
+}
+
+impl EthereumOrder {
+    pub(crate) fn assert_valid(&self) {
+        require!(self.amount.0 > 0, "Ethereum order amount must be positive");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eth_address_json() {
+        let address: EthAddress =
+            near_sdk::serde_json::from_str("\"0x52908400098527886E0F7030069857D2E4169EE7\"").unwrap();
+        assert_eq!(
+            near_sdk::serde_json::to_string(&address).unwrap(),
+            "\"0x52908400098527886e0f7030069857d2e4169ee7\""
+        );
+        assert!(near_sdk::serde_json::from_str::<EthAddress>("\"0x5290\"").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use near_sdk::serde::Serialize;
+use near_sdk::{log, AccountId, NearToken};
+
+use crate::access::{ResolverStatus, Role};
+use crate::pause::PausableMethod;
+use crate::Bytes32;
+
+const EVENT_STANDARD: &str = "fusion-escrow";
+const EVENT_VERSION: &str = "1.0.0";
+
+/// NEP-297 events emitted by `FusionEscrow`
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum FusionEvent<'a> {
+    ResolverStatusChanged {
+        resolver: &'a AccountId,
+        status: Option<ResolverStatus>, // None when revoked
+        by: &'a AccountId,
+    },
+    RoleGranted {
+        account: &'a AccountId,
+        role: Role,
+        by: &'a AccountId,
+    },
+    RoleRevoked {
+        account: &'a AccountId,
+        role: Role,
+        by: &'a AccountId,
+    },
+    OwnershipTransferProposed {
+        owner: &'a AccountId,
+        pending_owner: Option<&'a AccountId>,
+    },
+    OwnershipTransferred {
+        previous_owner: &'a AccountId,
+        new_owner: &'a AccountId,
+    },
+    BondDeposited {
+        resolver: &'a AccountId,
+        amount: NearToken,
+        total: NearToken,
+    },
+    UnbondRequested {
+        resolver: &'a AccountId,
+        amount: NearToken,
+        available_at: u64,
+    },
+    BondWithdrawn {
+        resolver: &'a AccountId,
+        amount: NearToken,
+    },
+    ResolverSlashed {
+        resolver: &'a AccountId,
+        maker: &'a AccountId,
+        ethereum_order_hash: &'a Bytes32,
+        amount: NearToken,
+    },
+    Paused {
+        method: PausableMethod,
+        by: &'a AccountId,
+    },
+    Unpaused {
+        method: PausableMethod,
+        by: &'a AccountId,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a FusionEvent<'a>,
+}
+
+impl FusionEvent<'_> {
+    pub fn emit(&self) {
+        let event = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_VERSION,
+            event: self,
+        };
+        log!(
+            "EVENT_JSON:{}",
+            near_sdk::serde_json::to_string(&event).expect("Failed to serialize event")
+        );
+    }
+}
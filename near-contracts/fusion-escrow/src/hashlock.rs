@@ -0,0 +1,130 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// 32-byte value (secret or hashlock), matching Solidity `bytes32`.
+/// JSON accepts a hex string (optionally 0x-prefixed) or a 32-element byte array,
+/// and always serializes as 0x-prefixed hex.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes32(pub [u8; 32]);
+
+impl Bytes32 {
+    /// Parse a 64-character hex string, with or without 0x prefix
+    pub fn from_hex(value: &str) -> Result<Self, String> {
+        let stripped = value.strip_prefix("0x").unwrap_or(value);
+        if stripped.len() != 64 {
+            return Err(format!("Expected 32 bytes of hex, got {} characters", stripped.len()));
+        }
+        let bytes = hex::decode(stripped).map_err(|e| format!("Invalid hex: {}", e))?;
+        Self::from_slice(&bytes)
+    }
+
+    /// Convert a byte slice, rejecting anything that is not exactly 32 bytes
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        let array: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| format!("Expected 32 bytes, got {}", bytes.len()))?;
+        Ok(Self(array))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0u8; 32]
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+impl std::fmt::Display for Bytes32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl Serialize for Bytes32 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes32 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(crate = "near_sdk::serde", untagged)]
+        enum Repr {
+            Hex(String),
+            Bytes(Vec<u8>),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Hex(value) => Bytes32::from_hex(&value),
+            Repr::Bytes(bytes) => Bytes32::from_slice(&bytes),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+/// Hash function used for hashlocks. Keccak256 matches `keccak256(abi.encode(secret))`
+/// in `FusionNearExtension.sol`; sha256 is for BTC/Lightning-style HTLCs.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "lowercase")]
+#[borsh(use_discriminant = true)]
+pub enum HashAlgorithm {
+    #[default]
+    Keccak256 = 0,
+    Sha256 = 1,
+}
+
+impl HashAlgorithm {
+    pub fn hash(&self, data: &[u8]) -> Bytes32 {
+        Bytes32(match self {
+            HashAlgorithm::Keccak256 => env::keccak256_array(data),
+            HashAlgorithm::Sha256 => env::sha256_array(data),
+        })
+    }
+
+    /// Check that `secret` unlocks `hashlock`
+    pub fn verify(&self, secret: &Bytes32, hashlock: &Bytes32) -> bool {
+        self.hash(&secret.0) == *hashlock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    const SECRET: &str = "0x4242424242424242424242424242424242424242424242424242424242424242";
+
+    #[test]
+    fn test_hash_vectors() {
+        testing_env!(VMContextBuilder::new().build());
+        let secret = Bytes32::from_hex(SECRET).unwrap();
+
+        // keccak256(abi.encode(bytes32 secret)) as computed by FusionNearExtension.sol
+        let keccak = Bytes32::from_hex("0x1874b9acfaca383a76e8f7253bdb183902f36254b1e5e452ac78228db63e93f3").unwrap();
+        let sha = Bytes32::from_hex("0x425ed4e4a36b30ea21b90e21c712c649e8214c29b7eaf68089d1039c6e55384c").unwrap();
+
+        assert!(HashAlgorithm::Keccak256.verify(&secret, &keccak));
+        assert!(HashAlgorithm::Sha256.verify(&secret, &sha));
+        assert!(!HashAlgorithm::Sha256.verify(&secret, &keccak));
+    }
+
+    #[test]
+    fn test_bytes32_json() {
+        let parsed: Bytes32 = near_sdk::serde_json::from_str(&format!("\"{}\"", SECRET)).unwrap();
+        assert_eq!(parsed, Bytes32([0x42; 32]));
+        assert_eq!(near_sdk::serde_json::to_string(&parsed).unwrap(), format!("\"{}\"", SECRET));
+
+        // Any casing, with or without 0x, parses to the same value
+        let mixed: Bytes32 = near_sdk::serde_json::from_str(&format!("\"0x{}\"", "aB".repeat(32))).unwrap();
+        let bare: Bytes32 = near_sdk::serde_json::from_str(&format!("\"{}\"", "AB".repeat(32))).unwrap();
+        assert_eq!(mixed, Bytes32([0xab; 32]));
+        assert_eq!(bare, mixed);
+
+        assert!(near_sdk::serde_json::from_str::<Bytes32>("\"0x42\"").is_err());
+        assert!(Bytes32::from_slice(&[0x42; 31]).is_err());
+    }
+}
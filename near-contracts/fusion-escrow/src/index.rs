@@ -0,0 +1,307 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::store::{IterableSet, LookupMap};
+use near_sdk::{near, AccountId};
+
+use crate::{page, Bytes32, CrossChainOrder, FusionEscrow, FusionEscrowExt, StorageKey, VersionedOrder};
+
+/// Lifecycle status used by the status index
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum OrderStatus {
+    Open,
+    Completed,
+    Cancelled,
+}
+
+impl CrossChainOrder {
+    pub fn status(&self) -> OrderStatus {
+        if self.completed {
+            OrderStatus::Completed
+        } else if self.cancelled {
+            OrderStatus::Cancelled
+        } else {
+            OrderStatus::Open
+        }
+    }
+}
+
+/// Secondary indexes over `orders`, kept in sync by `FusionEscrow::save_order`.
+/// The sets have no defined order: a removal moves the last entry into the freed
+/// slot, so pages can shift once an order changes status or is pruned.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OrderIndexes {
+    pub by_maker: LookupMap<AccountId, IterableSet<Bytes32>>,
+    pub by_resolver: LookupMap<AccountId, IterableSet<Bytes32>>,
+    pub by_account: LookupMap<AccountId, IterableSet<Bytes32>>, // Maker or resolver, each order once
+    pub open: IterableSet<Bytes32>,
+    pub completed: IterableSet<Bytes32>,
+    pub cancelled: IterableSet<Bytes32>,
+}
+
+impl Default for OrderIndexes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderIndexes {
+    pub fn new() -> Self {
+        Self {
+            by_maker: LookupMap::new(StorageKey::OrdersByMaker),
+            by_resolver: LookupMap::new(StorageKey::OrdersByResolver),
+            by_account: LookupMap::new(StorageKey::OrdersByAccount),
+            open: IterableSet::new(StorageKey::OpenOrders),
+            completed: IterableSet::new(StorageKey::CompletedOrders),
+            cancelled: IterableSet::new(StorageKey::CancelledOrders),
+        }
+    }
+
+    pub fn by_status(&self, status: OrderStatus) -> &IterableSet<Bytes32> {
+        match status {
+            OrderStatus::Open => &self.open,
+            OrderStatus::Completed => &self.completed,
+            OrderStatus::Cancelled => &self.cancelled,
+        }
+    }
+
+    fn by_status_mut(&mut self, status: OrderStatus) -> &mut IterableSet<Bytes32> {
+        match status {
+            OrderStatus::Open => &mut self.open,
+            OrderStatus::Completed => &mut self.completed,
+            OrderStatus::Cancelled => &mut self.cancelled,
+        }
+    }
+
+    fn insert_account(
+        map: &mut LookupMap<AccountId, IterableSet<Bytes32>>,
+        key: StorageKey,
+        account: &AccountId,
+        ethereum_order_hash: &Bytes32,
+    ) {
+        // Per-account set gets its own prefix: key + sha256(account)
+        let set = map
+            .entry(account.clone())
+            .or_insert_with(|| IterableSet::new(key.for_account(account)));
+        set.insert(*ethereum_order_hash);
+        set.flush();
+    }
+
+    fn remove_account(
+        map: &mut LookupMap<AccountId, IterableSet<Bytes32>>,
+        account: &AccountId,
+        ethereum_order_hash: &Bytes32,
+    ) {
+        if let Some(set) = map.get_mut(account) {
+            set.remove(ethereum_order_hash);
+            if set.is_empty() {
+                map.remove(account);
+            }
+        }
+    }
+
+    /// Index a newly created order
+    pub fn add(&mut self, ethereum_order_hash: &Bytes32, order: &CrossChainOrder) {
+        Self::insert_account(&mut self.by_maker, StorageKey::MakerOrders, &order.maker, ethereum_order_hash);
+        Self::insert_account(&mut self.by_resolver, StorageKey::ResolverOrders, &order.resolver, ethereum_order_hash);
+        Self::insert_account(&mut self.by_account, StorageKey::AccountOrders, &order.maker, ethereum_order_hash);
+        Self::insert_account(&mut self.by_account, StorageKey::AccountOrders, &order.resolver, ethereum_order_hash);
+        self.by_status_mut(order.status()).insert(*ethereum_order_hash);
+    }
+
+    /// Move an order between status sets after a state transition
+    pub fn update(&mut self, ethereum_order_hash: &Bytes32, previous: &CrossChainOrder, order: &CrossChainOrder) {
+        if previous.status() != order.status() {
+            self.by_status_mut(previous.status()).remove(ethereum_order_hash);
+            self.by_status_mut(order.status()).insert(*ethereum_order_hash);
+        }
+    }
+
+    /// Drop an order from every index
+    pub fn remove(&mut self, ethereum_order_hash: &Bytes32, order: &CrossChainOrder) {
+        Self::remove_account(&mut self.by_maker, &order.maker, ethereum_order_hash);
+        Self::remove_account(&mut self.by_resolver, &order.resolver, ethereum_order_hash);
+        Self::remove_account(&mut self.by_account, &order.maker, ethereum_order_hash);
+        Self::remove_account(&mut self.by_account, &order.resolver, ethereum_order_hash);
+        self.by_status_mut(order.status()).remove(ethereum_order_hash);
+    }
+
+    /// Write pending index changes to storage
+    pub fn flush(&mut self) {
+        self.by_maker.flush();
+        self.by_resolver.flush();
+        self.by_account.flush();
+        self.open.flush();
+        self.completed.flush();
+        self.cancelled.flush();
+    }
+}
+
+#[near]
+impl FusionEscrow {
+    /// Orders where `account` is maker or resolver, in no particular order. Orders the
+    /// account resolves for itself are listed once.
+    pub fn get_orders_for_account(
+        &self,
+        account: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<CrossChainOrder> {
+        self.indexes
+            .by_account
+            .get(&account)
+            .map_or_else(Vec::new, |set| self.orders_page(set, from_index, limit))
+    }
+
+    pub fn get_orders_by_maker(&self, maker: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<CrossChainOrder> {
+        self.indexes
+            .by_maker
+            .get(&maker)
+            .map_or_else(Vec::new, |set| self.orders_page(set, from_index, limit))
+    }
+
+    pub fn get_orders_by_resolver(&self, resolver: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<CrossChainOrder> {
+        self.indexes
+            .by_resolver
+            .get(&resolver)
+            .map_or_else(Vec::new, |set| self.orders_page(set, from_index, limit))
+    }
+
+    pub fn get_orders_by_status(&self, status: OrderStatus, from_index: Option<u64>, limit: Option<u64>) -> Vec<CrossChainOrder> {
+        self.orders_page(self.indexes.by_status(status), from_index, limit)
+    }
+
+    pub fn get_open_orders(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<CrossChainOrder> {
+        self.get_orders_by_status(OrderStatus::Open, from_index, limit)
+    }
+
+    pub fn get_orders_count(&self, status: Option<OrderStatus>) -> u64 {
+        match status {
+            Some(status) => u64::from(self.indexes.by_status(status).len()),
+            None => u64::from(self.orders.len()),
+        }
+    }
+}
+
+impl FusionEscrow {
+    /// Single write path for orders so the secondary indexes never drift.
+    /// Changes are flushed right away so callers can measure the storage they use.
+    pub(crate) fn save_order(&mut self, ethereum_order_hash: &Bytes32, order: &CrossChainOrder) {
+        match self.orders.insert(*ethereum_order_hash, VersionedOrder::V2(order.clone())) {
+            None => self.indexes.add(ethereum_order_hash, order),
+            Some(previous) => self.indexes.update(ethereum_order_hash, &CrossChainOrder::from(previous), order),
+        }
+        self.orders.flush();
+        self.indexes.flush();
+    }
+
+    /// Read an order, upgrading older stored layouts
+    pub(crate) fn load_order(&self, ethereum_order_hash: &Bytes32) -> Option<CrossChainOrder> {
+        self.orders.get(ethereum_order_hash).cloned().map(CrossChainOrder::from)
+    }
+
+    fn orders_page(&self, set: &IterableSet<Bytes32>, from_index: Option<u64>, limit: Option<u64>) -> Vec<CrossChainOrder> {
+        page(set.iter(), set.len(), from_index, limit)
+            .filter_map(|hash| self.load_order(hash))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::{page_range, ORDER_RETENTION_PERIOD_NS};
+    use near_sdk::NearToken;
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    fn hashes(orders: Vec<CrossChainOrder>) -> Vec<Bytes32> {
+        orders.into_iter().map(|order| order.ethereum_order_hash).collect()
+    }
+
+    #[test]
+    fn test_status_indexes_follow_settlement() {
+        let mut contract = setup();
+        for n in 1..=3 {
+            near_order(&mut contract, n, NearToken::from_near(1), DEADLINE_SECONDS);
+        }
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Open)), 3);
+
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(2), secret(2));
+        call(resolver(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * NS);
+        let _ = contract.cancel_order(order_hash(3));
+
+        assert_eq!(hashes(contract.get_open_orders(None, None)), vec![order_hash(1)]);
+        assert_eq!(hashes(contract.get_orders_by_status(OrderStatus::Completed, None, None)), vec![order_hash(2)]);
+        assert_eq!(hashes(contract.get_orders_by_status(OrderStatus::Cancelled, None, None)), vec![order_hash(3)]);
+        assert_eq!(contract.get_orders_count(None), 3);
+    }
+
+    #[test]
+    fn test_account_indexes_paginate() {
+        let mut contract = setup();
+        for n in 1..=3 {
+            near_order(&mut contract, n, NearToken::from_near(1), DEADLINE_SECONDS);
+        }
+
+        assert_eq!(hashes(contract.get_orders_by_maker(maker(), Some(1), Some(1))), vec![order_hash(2)]);
+        assert_eq!(hashes(contract.get_orders_by_resolver(resolver(), Some(2), None)), vec![order_hash(3)]);
+        assert!(contract.get_orders_by_maker(resolver(), None, None).is_empty());
+        assert!(contract.get_orders_by_maker(maker(), Some(5), None).is_empty());
+    }
+
+    #[test]
+    fn test_orders_for_account_lists_self_resolved_orders_once() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(1), DEADLINE_SECONDS);
+        // The resolver sells NEAR and resolves the order itself
+        call(resolver(), NearToken::from_near(1), 0);
+        contract.create_near_to_eth_order(order_hash(2), resolver(), hashlock(2), None, DEADLINE_SECONDS, None, None, None);
+
+        let orders = contract.get_orders_for_account(resolver(), None, None);
+        assert_eq!(hashes(orders), vec![order_hash(1), order_hash(2)]);
+        assert_eq!(hashes(contract.get_orders_for_account(maker(), None, None)), vec![order_hash(1)]);
+    }
+
+    #[test]
+    fn test_orders_for_account_page_spans_self_resolved_orders() {
+        let mut contract = setup();
+        for n in 1..=4 {
+            if n % 2 == 0 {
+                call(resolver(), NearToken::from_near(1), 0);
+                contract.create_near_to_eth_order(order_hash(n), resolver(), hashlock(n), None, DEADLINE_SECONDS, None, None, None);
+            } else {
+                near_order(&mut contract, n, NearToken::from_near(1), DEADLINE_SECONDS);
+            }
+        }
+
+        // Full pages across the orders the resolver makes and resolves itself
+        let page = contract.get_orders_for_account(resolver(), Some(1), Some(2));
+        assert_eq!(hashes(page), vec![order_hash(2), order_hash(3)]);
+        let page = contract.get_orders_for_account(resolver(), Some(3), Some(2));
+        assert_eq!(hashes(page), vec![order_hash(4)]);
+    }
+
+    #[test]
+    fn test_pruned_orders_leave_indexes() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(1), DEADLINE_SECONDS);
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+
+        call(stranger(), NearToken::from_yoctonear(0), NS + ORDER_RETENTION_PERIOD_NS);
+        assert!(contract.prune_orders(vec![order_hash(1)])[0].success);
+        assert!(contract.get_orders_by_maker(maker(), None, None).is_empty());
+        assert!(contract.get_orders_for_account(resolver(), None, None).is_empty());
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Completed)), 0);
+    }
+
+    #[test]
+    fn test_page_range_caps_limit() {
+        assert_eq!(page_range(250, None, Some(500)), 0..100);
+        assert_eq!(page_range(250, Some(200), None), 200..250);
+        assert_eq!(page_range(5, Some(10), Some(3)), 5..5);
+    }
+}
@@ -1,192 +1,944 @@
+// Order creation methods take the order parameters individually, also in the generated `ext` interface
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
+use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::store::{IterableMap, IterableSet, LookupMap};
 use near_sdk::{
-    env, near_bindgen, AccountId, NearToken, Promise, PanicOnDefault, 
+    env, near, AccountId, BorshStorageKey, Gas, NearToken, Promise, PanicOnDefault, 
     require, log
 };
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+mod access;
+mod accounting;
+mod assets;
+mod auction;
+mod bonding;
+mod chains;
+mod ethereum;
+mod events;
+mod hashlock;
+mod index;
+mod limits;
+mod migration;
+mod orderbook;
+mod pause;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod test_utils;
+
+pub use access::{ResolverInfo, ResolverStatus, Role};
+pub use accounting::{AssetTotals, AssetTotalsView, BalanceCheck, OrderCounts};
+pub use assets::{Asset, FtOrderMsg};
+pub use auction::{AuctionPoint, DutchAuction, MAX_AUCTION_POINTS, RATE_PRECISION};
+pub use bonding::{BondConfig, ResolverBond};
+pub use chains::{ChainInfo, SwapDirection, DEFAULT_COUNTERPARTY_CHAIN_ID};
+pub use ethereum::{EthAddress, EthereumOrder};
+pub use hashlock::{Bytes32, HashAlgorithm};
+pub use index::{OrderIndexes, OrderStatus};
+pub use limits::{LimitError, ResolverLimits, ResolverUsage, ResolverUtilization};
+pub use migration::{
+    CrossChainOrderV1, FusionEscrowV1, MigrationProgress, MigrationStatus, VersionedOrder, STATE_VERSION,
+};
+pub use orderbook::{IntentParams, IntentTerms, OpenIntent, INTENT_LOCK_PERIOD_NS};
+pub use pause::{PausableMethod, PAUSABLE_METHODS};
+use pause::MIGRATION_IN_PROGRESS;
+pub use stats::{AssetAmount, AutoSuspendConfig, ResolverStats, ResolverStatsView};
+pub use storage::{ArchivedOrder, DEFAULT_MIN_NEAR_ORDER_AMOUNT, ORDER_RETENTION_PERIOD_NS};
+
+// Upper bound for paginated views
+const MAX_PAGE_SIZE: u64 = 100;
+
+// Gas reserved for the state changes and NEAR transfers of one batch item. Claims
+// and cancellations also reserve `gas_for_payouts` for their token payouts.
+const GAS_PER_BATCH_ITEM: Gas = Gas::from_tgas(10);
+
+// Time after the deadline during which only the funder can cancel (matches
+// TIMELOCK_DURATION in FusionNearExtension.sol); afterwards anyone can trigger the refund
+const CANCELLATION_GRACE_PERIOD_NS: u64 = 1800 * 1_000_000_000;
+
+// Share of the order amount paid to a third party that triggers a public refund
+const PUBLIC_CANCEL_REWARD_BPS: u128 = 10;
+
+// Time after creation during which only the beneficiary or the resolver can claim
+const EXCLUSIVE_CLAIM_PERIOD_NS: u64 = 600 * 1_000_000_000;
+
+// Share of the order amount paid to a third party that completes a claim publicly
+const PUBLIC_CLAIM_FEE_BPS: u128 = 10;
+
+// Longest order lifetime a creator can request
+pub const MAX_DEADLINE_SECONDS: u64 = 7 * 24 * 3600;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct CrossChainOrder {
-    pub ethereum_order_hash: String,
-    pub direction: String,
+    pub ethereum_order_hash: Bytes32,
+    pub direction: SwapDirection,
+    pub counterparty_chain_id: u64,
     pub maker: AccountId,
     pub resolver: AccountId,
-    pub amount: NearToken,
-    pub hashlock: String,
+    pub asset: Asset,
+    pub amount: U128,
+    pub hashlock: Bytes32,
+    pub hash_algorithm: HashAlgorithm,
     pub deadline: u64,
+    pub exclusive_until: u64,
     pub completed: bool,
     pub cancelled: bool,
-    pub revealed_secret: Option<String>,
+    pub revealed_secret: Option<Bytes32>,
+    pub storage_payer: AccountId,
+    pub storage_deposit: U128,
+    pub safety_deposit: U128,            // NEAR paid to whoever settles the order in a public phase
+    pub auction_rate: Option<U128>,      // Winning rate if the order was taken from a Dutch auction
+    pub ethereum_order: Option<EthereumOrder>, // Counterparty order data, if the creator supplied it
+    pub intent: Option<IntentTerms>,     // Destination terms if the order was committed from an intent
+    pub created_at: u64,                 // Migration time for orders from the original layout
+    pub settled_at: u64,                 // Completion or cancellation time, 0 while open
+    pub claim_pause_epoch: u64,          // Contract's claim pause epoch when the order was created
+}
+
+impl CrossChainOrder {
+    /// Account that receives the funds on claim: the maker for eth_to_near, the resolver for near_to_eth
+    pub fn beneficiary(&self) -> &AccountId {
+        match self.direction {
+            SwapDirection::EthToNear => &self.maker,
+            SwapDirection::NearToEth => &self.resolver,
+        }
+    }
+
+    /// Account that locked the funds: the resolver for eth_to_near, the maker for near_to_eth
+    pub fn funder(&self) -> &AccountId {
+        match self.direction {
+            SwapDirection::EthToNear => &self.resolver,
+            SwapDirection::NearToEth => &self.maker,
+        }
+    }
+}
+
+/// Parameters shared by every order creation path (attached NEAR or `ft_transfer_call`)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OrderParams {
+    pub ethereum_order_hash: Bytes32,    // JSON: hex, with or without 0x in any casing
+    pub hashlock: Bytes32,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub deadline_seconds: u64,
+    pub counterparty_chain_id: Option<u64>,
+    pub safety_deposit: Option<U128>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchClaim {
+    pub ethereum_order_hash: Bytes32,
+    pub secret: Bytes32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchItemResult {
+    pub ethereum_order_hash: Bytes32,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Storage prefixes. The original layout kept its orders and resolvers under
+/// b"o" and b"r"; its orders stay there until `migrate_batch` has moved them.
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum StorageKey {
+    Orders = 0,
+    Roles = 1,
+    Resolvers = 2,
+    Chains = 3,
+    Auctions = 4,
+    Intents = 5,
+    OrdersByMaker = 6,
+    MakerOrders = 7,          // Followed by sha256(maker)
+    OrdersByResolver = 8,
+    ResolverOrders = 9,       // Followed by sha256(resolver)
+    OpenOrders = 10,
+    CompletedOrders = 11,
+    CancelledOrders = 12,
+    ResolverStats = 13,
+    AssetTotals = 14,
+    ResolverLimits = 15,
+    ResolverUsage = 16,
+    ArchivedOrders = 17,
+    OrdersByAccount = 18,
+    AccountOrders = 19,       // Followed by sha256(account)
+    AcceptedTokens = 20,
+    Bonds = 21,
+    FailedPayouts = 22,
+    StorageBalances = 23,
+    MinOrderAmounts = 24,
+}
+
+impl StorageKey {
+    /// Prefix of a per-account collection nested under this key
+    pub fn for_account(self, account: &AccountId) -> Vec<u8> {
+        let mut prefix = vec![self as u8];
+        prefix.extend(env::sha256(account.as_bytes()));
+        prefix
+    }
 }
 
-#[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
 pub struct FusionEscrow {
-    pub orders: UnorderedMap<String, CrossChainOrder>,
+    pub orders: IterableMap<Bytes32, VersionedOrder>,
+    pub indexes: OrderIndexes,
     pub owner: AccountId,
-    pub authorized_resolvers: UnorderedMap<AccountId, bool>,
+    pub pending_owner: Option<AccountId>,
+    pub roles: LookupMap<AccountId, Vec<Role>>,
+    pub authorized_resolvers: IterableMap<AccountId, ResolverStatus>,
+    pub bonds: LookupMap<AccountId, ResolverBond>,
+    pub bond_config: BondConfig,
+    pub chains: IterableMap<u64, ChainInfo>,
+    pub failed_payouts: LookupMap<(AccountId, AccountId), u128>,
+    pub storage_balances: LookupMap<AccountId, u128>,
+    pub min_order_amounts: LookupMap<Asset, u128>,
+    pub archived_orders: LookupMap<Bytes32, ArchivedOrder>,
+    pub auctions: IterableMap<Bytes32, DutchAuction>,
+    pub intents: IterableMap<u64, OpenIntent>,
+    pub next_intent_id: u64,
+    pub paused_methods: Vec<PausableMethod>,
+    pub claim_pause_epoch: u64,
+    pub resolver_stats: LookupMap<AccountId, ResolverStats>,
+    pub auto_suspend: Option<AutoSuspendConfig>,
+    pub asset_totals: IterableMap<(Asset, SwapDirection), AssetTotals>,
+    pub order_counts: OrderCounts,
+    pub locked_safety_deposits: u128,
+    pub total_bonds: u128,
+    pub total_storage_balances: u128,
+    pub default_resolver_limits: ResolverLimits,
+    pub resolver_limits: LookupMap<AccountId, ResolverLimits>,
+    pub resolver_usage: LookupMap<AccountId, ResolverUsage>,
+    pub accepted_tokens: IterableSet<AccountId>,
+    pub migration: Option<MigrationProgress>,
 }
 
-#[near_bindgen]
+#[near]
 impl FusionEscrow {
     #[init]
     pub fn new() -> Self {
-        let mut contract = Self {
-            orders: UnorderedMap::new(b"o"),
-            owner: env::predecessor_account_id(),
-            authorized_resolvers: UnorderedMap::new(b"r"),
-        };
-        
-        contract.authorized_resolvers.insert(&env::predecessor_account_id(), &true);
-        contract
-    }
-
-    pub fn authorize_resolver(&mut self, resolver: AccountId) {
-        require!(env::predecessor_account_id() == self.owner, "Only owner can authorize");
-        self.authorized_resolvers.insert(&resolver, &true);
-        log!("Authorized resolver: {}", resolver);
+        Self::write_state_version();
+        Self::init(env::predecessor_account_id())
     }
 
     #[payable]
     pub fn create_eth_to_near_order(
         &mut self,
-        ethereum_order_hash: String,
+        ethereum_order_hash: Bytes32,
         maker: AccountId,
-        hashlock: String,
+        hashlock: Bytes32,
+        hash_algorithm: Option<HashAlgorithm>,
         deadline_seconds: u64,
+        counterparty_chain_id: Option<u64>,
+        safety_deposit: Option<U128>,
+        ethereum_order: Option<EthereumOrder>,
     ) {
-        require!(
-            self.authorized_resolvers.get(&env::predecessor_account_id()).unwrap_or(false),
-            "Not authorized resolver"
-        );
-        
-        let amount = env::attached_deposit();
-        require!(amount.as_yoctonear() > 0, "Must attach NEAR tokens");
-        require!(self.orders.get(&ethereum_order_hash).is_none(), "Order already exists");
-        
-        let deadline = env::block_timestamp() + (deadline_seconds * 1_000_000_000);
-        
-        let order = CrossChainOrder {
-            ethereum_order_hash: ethereum_order_hash.clone(),
-            direction: "eth_to_near".to_string(),
-            maker,
-            resolver: env::predecessor_account_id(),
-            amount,
+        let params = OrderParams {
+            ethereum_order_hash,
             hashlock,
-            deadline,
-            completed: false,
-            cancelled: false,
-            revealed_secret: None,
+            hash_algorithm,
+            deadline_seconds,
+            counterparty_chain_id,
+            safety_deposit,
         };
-        
-        self.orders.insert(&ethereum_order_hash, &order);
-        
-        log!(
-            "ETH->NEAR escrow created: {} yoctoNEAR for order {}",
-            amount, ethereum_order_hash
+        let amount = env::attached_deposit().as_yoctonear();
+        let resolver = env::predecessor_account_id();
+        self.create_order(
+            params, SwapDirection::EthToNear, maker, resolver.clone(), Asset::Near, amount,
+            resolver, None, ethereum_order, None,
         );
     }
 
     #[payable]
     pub fn create_near_to_eth_order(
         &mut self,
-        ethereum_order_hash: String,
+        ethereum_order_hash: Bytes32,
         resolver: AccountId,
-        hashlock: String,
+        hashlock: Bytes32,
+        hash_algorithm: Option<HashAlgorithm>,
         deadline_seconds: u64,
+        counterparty_chain_id: Option<u64>,
+        safety_deposit: Option<U128>,
+        ethereum_order: Option<EthereumOrder>,
     ) {
-        let amount = env::attached_deposit();
-        require!(amount.as_yoctonear() > 0, "Must attach NEAR tokens");
-        require!(self.orders.get(&ethereum_order_hash).is_none(), "Order already exists");
-        
-        let deadline = env::block_timestamp() + (deadline_seconds * 1_000_000_000);
-        
-        let order = CrossChainOrder {
-            ethereum_order_hash: ethereum_order_hash.clone(),
-            direction: "near_to_eth".to_string(),
-            maker: env::predecessor_account_id(),
-            resolver,
-            amount,
+        let params = OrderParams {
+            ethereum_order_hash,
             hashlock,
-            deadline,
-            completed: false,
-            cancelled: false,
-            revealed_secret: None,
+            hash_algorithm,
+            deadline_seconds,
+            counterparty_chain_id,
+            safety_deposit,
         };
-        
-        self.orders.insert(&ethereum_order_hash, &order);
-        
-        log!(
-            "NEAR->ETH escrow created: {} yoctoNEAR for order {}",
-            amount, ethereum_order_hash
+        let amount = env::attached_deposit().as_yoctonear();
+        let maker = env::predecessor_account_id();
+        self.create_order(
+            params, SwapDirection::NearToEth, maker.clone(), resolver, Asset::Near, amount,
+            maker, None, ethereum_order, None,
         );
     }
 
     pub fn claim_with_secret(
         &mut self,
-        ethereum_order_hash: String,
-        secret: String,
+        ethereum_order_hash: Bytes32,
+        secret: Bytes32,
     ) -> Promise {
-        let mut order = self.orders.get(&ethereum_order_hash)
-            .expect("Order does not exist");
+        self.try_claim(&ethereum_order_hash, secret)
+            .unwrap_or_else(|error| env::panic_str(&error))
+    }
+
+    /// Claim many orders in one transaction. Each item is processed independently:
+    /// a failing item is reported in the result without aborting the others.
+    pub fn batch_claim_with_secret(&mut self, claims: Vec<BatchClaim>) -> Vec<BatchItemResult> {
+        claims
+            .into_iter()
+            .map(|claim| {
+                let result = self.try_claim(&claim.ethereum_order_hash, claim.secret).map(|_| ());
+                Self::batch_item_result(claim.ethereum_order_hash, result)
+            })
+            .collect()
+    }
+
+    /// Cancel an expired order and refund the funder. The funder can cancel as soon as
+    /// the deadline passes; after the grace period anyone can trigger the refund and
+    /// receives the safety deposit, or a small reward out of the order amount if the
+    /// order has none.
+    pub fn cancel_order(&mut self, ethereum_order_hash: Bytes32) -> Promise {
+        self.try_cancel(&ethereum_order_hash)
+            .unwrap_or_else(|error| env::panic_str(&error))
+    }
+
+    /// Cancel many expired orders in one transaction, reporting per-item results
+    pub fn batch_cancel_orders(&mut self, ethereum_order_hashes: Vec<Bytes32>) -> Vec<BatchItemResult> {
+        ethereum_order_hashes
+            .into_iter()
+            .map(|ethereum_order_hash| {
+                let result = self.try_cancel(&ethereum_order_hash).map(|_| ());
+                Self::batch_item_result(ethereum_order_hash, result)
+            })
+            .collect()
+    }
+
+    pub fn get_order(&self, ethereum_order_hash: Bytes32) -> Option<CrossChainOrder> {
+        self.load_order(&ethereum_order_hash)
+    }
+
+    fn try_claim(&mut self, ethereum_order_hash: &Bytes32, secret: Bytes32) -> Result<Promise, String> {
+        if self.migration.is_some() {
+            return Err(MIGRATION_IN_PROGRESS.to_string());
+        }
+        if self.is_paused(PausableMethod::Claim) {
+            return Err("Method paused: Claim".to_string());
+        }
+        let mut order = self.load_order(ethereum_order_hash)
+            .ok_or("Order does not exist")?;
+        
+        if order.completed {
+            return Err("Order already completed".to_string());
+        }
+        if order.cancelled {
+            return Err("Order cancelled".to_string());
+        }
+        if env::block_timestamp() > order.deadline {
+            return Err("Order expired".to_string());
+        }
         
-        require!(!order.completed, "Order already completed");
-        require!(!order.cancelled, "Order cancelled");
-        require!(env::block_timestamp() <= order.deadline, "Order expired");
+        if !order.hash_algorithm.verify(&secret, &order.hashlock) {
+            return Err("Invalid secret".to_string());
+        }
         
-        // For demo, using simple comparison - in production would use proper hash verification
-        let computed_hash = self.compute_hashlock(&secret);
-        require!(computed_hash == order.hashlock, "Invalid secret");
+        // Beneficiary and resolver have an exclusive window, then anyone can finish the claim.
+        // The maker is a party too: on NEAR->ETH it already knows the secret, so it must not
+        // be able to collect the public fee out of the resolver's payout.
+        let caller = env::predecessor_account_id();
+        let beneficiary = order.beneficiary().clone();
+        let is_party = caller == beneficiary || caller == order.resolver || caller == order.maker;
+        if !is_party && env::block_timestamp() <= order.exclusive_until {
+            return Err("Only beneficiary or resolver can claim during exclusive period".to_string());
+        }
+        // A safety deposit is the public claimer's reward, the principal is then paid in full
+        let fee = if is_party || order.safety_deposit.0 > 0 {
+            0
+        } else {
+            order.amount.0 * PUBLIC_CLAIM_FEE_BPS / 10_000
+        };
+        Self::check_gas_for_item(&order.asset, if fee > 0 { 2 } else { 1 })?;
         
         order.completed = true;
-        order.revealed_secret = Some(secret.clone());
-        self.orders.insert(&ethereum_order_hash, &order);
+        order.settled_at = env::block_timestamp();
+        order.revealed_secret = Some(secret);
+        self.save_order(ethereum_order_hash, &order);
+        self.record_order_completed(&order);
+        self.track_order_settled(&order);
+        self.release_resolver_capacity(&order);
         
-        if order.direction == "eth_to_near" {
+        let payout = order.amount.0 - fee;
+        if order.direction == SwapDirection::EthToNear {
             log!(
-                "ETH->NEAR completed: Transferring {} yoctoNEAR to {}",
-                order.amount, order.maker
+                "ETH->NEAR completed: Transferring {} {} to {}",
+                payout, order.asset, beneficiary
             );
-            Promise::new(order.maker).transfer(order.amount)
         } else {
             log!(
-                "NEAR->ETH completed: Transferring {} yoctoNEAR to resolver {}",
-                order.amount, order.resolver
+                "NEAR->ETH completed: Transferring {} {} to resolver {}",
+                payout, order.asset, beneficiary
             );
-            Promise::new(order.resolver).transfer(order.amount)
         }
+        
+        let mut transfers = self.transfer_asset(&order.asset, beneficiary, payout);
+        if fee > 0 {
+            log!("Public claim fee: {} {} to {}", fee, order.asset, caller);
+            transfers = transfers.and(self.transfer_asset(&order.asset, caller.clone(), fee));
+        }
+        if let Some(deposit) = Self::release_safety_deposit(&order, caller, !is_party) {
+            transfers = transfers.and(deposit);
+        }
+        Ok(transfers)
     }
 
-    pub fn get_order(&self, ethereum_order_hash: String) -> Option<CrossChainOrder> {
-        self.orders.get(&ethereum_order_hash)
+    fn try_cancel(&mut self, ethereum_order_hash: &Bytes32) -> Result<Promise, String> {
+        if self.migration.is_some() {
+            return Err(MIGRATION_IN_PROGRESS.to_string());
+        }
+        let mut order = self.load_order(ethereum_order_hash)
+            .ok_or("Order does not exist")?;
+        
+        if order.completed {
+            return Err("Order already completed".to_string());
+        }
+        if order.cancelled {
+            return Err("Order already cancelled".to_string());
+        }
+        
+        let now = env::block_timestamp();
+        if now <= order.deadline {
+            return Err("Order not expired yet".to_string());
+        }
+        
+        let caller = env::predecessor_account_id();
+        let funder = order.funder().clone();
+        let public_cancel = caller != funder;
+        if public_cancel && now <= order.deadline + CANCELLATION_GRACE_PERIOD_NS {
+            return Err("Only funder can cancel during grace period".to_string());
+        }
+        // A safety deposit is the public canceller's reward, the principal is then refunded in full
+        let reward = if !public_cancel || order.safety_deposit.0 > 0 {
+            0
+        } else {
+            order.amount.0 * PUBLIC_CANCEL_REWARD_BPS / 10_000
+        };
+        Self::check_gas_for_item(&order.asset, if reward > 0 { 2 } else { 1 })?;
+        // Orders migrated from the original layout have no usable hashlock, so the
+        // resolver could never have claimed them
+        let claimable = !order.hashlock.is_zero();
+        
+        order.cancelled = true;
+        order.settled_at = now;
+        self.save_order(ethereum_order_hash, &order);
+        if claimable {
+            self.record_order_expired(&order);
+        }
+        self.track_order_settled(&order);
+        self.release_resolver_capacity(&order);
+        
+        let refund = order.amount.0 - reward;
+        log!(
+            "Order {} cancelled: Refunding {} {} to {}",
+            ethereum_order_hash, refund, order.asset, funder
+        );
+        
+        let mut transfers = self.transfer_asset(&order.asset, funder, refund);
+        if reward > 0 {
+            log!("Public cancellation reward: {} {} to {}", reward, order.asset, caller);
+            transfers = transfers.and(self.transfer_asset(&order.asset, caller.clone(), reward));
+        }
+        if let Some(deposit) = Self::release_safety_deposit(&order, caller, public_cancel) {
+            transfers = transfers.and(deposit);
+        }
+        
+        // The resolver committed to an ETH->NEAR order and let it expire, unless a
+        // claim pause may have kept it from claiming in time
+        if order.direction == SwapDirection::EthToNear && claimable {
+            if self.claims_paused_since(order.claim_pause_epoch) {
+                log!("Claims were paused during order {}: Resolver not slashed", ethereum_order_hash);
+            } else if let Some(slash) = self.slash_resolver(&order.resolver, &order.maker, ethereum_order_hash) {
+                transfers = transfers.and(slash);
+            }
+        }
+        Ok(transfers)
     }
 
-    pub fn get_orders_for_account(&self, account: AccountId) -> Vec<CrossChainOrder> {
-        let mut result = Vec::new();
-        for (_, order) in self.orders.iter() {
-            if order.maker == account || order.resolver == account {
-                result.push(order);
-            }
+    /// Shared creation path for NEAR-funded and token-funded orders. The storage the
+    /// order adds is measured and charged to the prepaid balance of `storage_payer`,
+    /// who gets the order's own share back when the settled order is pruned. The
+    /// optional safety deposit is charged to the same balance, so `amount` is locked
+    /// exactly as attached.
+    pub(crate) fn create_order(
+        &mut self,
+        params: OrderParams,
+        direction: SwapDirection,
+        maker: AccountId,
+        resolver: AccountId,
+        asset: Asset,
+        amount: u128,
+        storage_payer: AccountId,
+        auction_rate: Option<U128>,
+        ethereum_order: Option<EthereumOrder>,
+        intent: Option<IntentTerms>,
+    ) {
+        let OrderParams {
+            ethereum_order_hash,
+            hashlock,
+            hash_algorithm,
+            deadline_seconds,
+            counterparty_chain_id,
+            safety_deposit,
+        } = params;
+        let safety_deposit = safety_deposit.map_or(0, |deposit| deposit.0);
+        self.assert_not_paused(PausableMethod::CreateOrder);
+        if let Some(ethereum_order) = &ethereum_order {
+            ethereum_order.assert_valid();
         }
-        result
+        
+        // ETH->NEAR orders are funded by the resolver, who must be authorized
+        if direction == SwapDirection::EthToNear {
+            require!(self.is_authorized_resolver(resolver.clone()), "Not authorized resolver");
+        }
+        
+        require!(amount > 0, "Must attach tokens");
+        require!(!self.is_order_hash_taken(&ethereum_order_hash), "Order already exists");
+        require!(!hashlock.is_zero(), "Invalid hashlock");
+        let counterparty_chain_id = counterparty_chain_id.unwrap_or(DEFAULT_COUNTERPARTY_CHAIN_ID);
+        self.assert_chain_enabled(counterparty_chain_id);
+        
+        require!(
+            deadline_seconds <= MAX_DEADLINE_SECONDS,
+            format!("Deadline exceeds maximum of {} seconds", MAX_DEADLINE_SECONDS)
+        );
+        let deadline_ns = deadline_seconds
+            .checked_mul(1_000_000_000)
+            .unwrap_or_else(|| env::panic_str("Deadline overflows"));
+        let deadline = env::block_timestamp()
+            .checked_add(deadline_ns)
+            .unwrap_or_else(|| env::panic_str("Deadline overflows"));
+        // An unbond requested while the order is open only becomes withdrawable after the
+        // order expired and its funder had the grace period to cancel, and slash, it
+        if direction == SwapDirection::EthToNear {
+            require!(
+                deadline_ns.saturating_add(CANCELLATION_GRACE_PERIOD_NS) < self.bond_config.unbonding_delay_ns,
+                "Deadline exceeds unbonding delay"
+            );
+        }
+        let exclusive_until = std::cmp::min(env::block_timestamp() + EXCLUSIVE_CLAIM_PERIOD_NS, deadline);
+        
+        let mut order = CrossChainOrder {
+            ethereum_order_hash,
+            direction,
+            counterparty_chain_id,
+            maker,
+            resolver,
+            asset,
+            amount: U128(amount),
+            hashlock,
+            hash_algorithm: hash_algorithm.unwrap_or_default(),
+            deadline,
+            exclusive_until,
+            completed: false,
+            cancelled: false,
+            revealed_secret: None,
+            storage_payer,
+            storage_deposit: U128(0),
+            safety_deposit: U128(safety_deposit),
+            auction_rate,
+            ethereum_order,
+            intent,
+            created_at: env::block_timestamp(),
+            settled_at: 0,
+            claim_pause_epoch: self.claim_pause_epoch,
+        };
+        
+        require!(amount >= self.min_order_amount(&order.asset), "Order amount below minimum");
+        
+        // The stats, totals and usage entries outlive the order, so they are charged
+        // but only the order's own storage is refunded when it is pruned
+        let initial_storage = env::storage_usage();
+        self.save_order(&ethereum_order_hash, &order);
+        let order_storage_cost = Self::storage_cost_since(initial_storage);
+        self.record_order_created(&order);
+        self.track_order_created(&order);
+        self.reserve_resolver_capacity(&order);
+        self.resolver_stats.flush();
+        self.asset_totals.flush();
+        self.resolver_usage.flush();
+        let storage_cost = Self::storage_cost_since(initial_storage);
+        self.charge_storage_balance(&order.storage_payer, storage_cost + safety_deposit);
+        
+        // Same encoded size as the measured entry, so the rewrite costs no extra storage
+        order.storage_deposit = U128(order_storage_cost);
+        self.save_order(&ethereum_order_hash, &order);
+        
+        let label = match direction {
+            SwapDirection::EthToNear => "ETH->NEAR",
+            SwapDirection::NearToEth => "NEAR->ETH",
+        };
+        log!(
+            "{} escrow created: {} {} for order {}",
+            label, amount, order.asset, ethereum_order_hash
+        );
     }
 
-    pub fn is_authorized_resolver(&self, resolver: AccountId) -> bool {
-        self.authorized_resolvers.get(&resolver).unwrap_or(false)
+    /// Whether an order, archive entry or auction already uses this hash
+    pub(crate) fn is_order_hash_taken(&self, ethereum_order_hash: &Bytes32) -> bool {
+        self.orders.contains_key(ethereum_order_hash)
+            || self.archived_orders.contains_key(ethereum_order_hash)
+            || self.auctions.contains_key(ethereum_order_hash)
     }
 
-    fn compute_hashlock(&self, secret: &str) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        secret.hash(&mut hasher);
-        format!("{:x}", hasher.finish())
+    /// Safety deposit goes to the caller when they settled the order in a public
+    /// phase, otherwise back to the account that posted it
+    fn release_safety_deposit(order: &CrossChainOrder, caller: AccountId, public_phase: bool) -> Option<Promise> {
+        if order.safety_deposit.0 == 0 {
+            return None;
+        }
+        let receiver = if public_phase { caller } else { order.storage_payer.clone() };
+        log!("Safety deposit: {} yoctoNEAR to {}", order.safety_deposit.0, receiver);
+        Some(Promise::new(receiver).transfer(NearToken::from_yoctonear(order.safety_deposit.0)))
+    }
+
+    pub(crate) fn has_gas_for_batch_item() -> bool {
+        env::prepaid_gas().saturating_sub(env::used_gas()) >= GAS_PER_BATCH_ITEM
+    }
+
+    /// Fail before any state change when the remaining gas cannot cover an item
+    /// that makes `payouts` transfers of `asset`
+    fn check_gas_for_item(asset: &Asset, payouts: u64) -> Result<(), String> {
+        let required = GAS_PER_BATCH_ITEM.saturating_add(Self::gas_for_payouts(asset, payouts));
+        if env::prepaid_gas().saturating_sub(env::used_gas()) < required {
+            return Err(format!("Insufficient gas for batch item, {} Tgas required", required.as_tgas()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn batch_item_result(ethereum_order_hash: Bytes32, result: Result<(), String>) -> BatchItemResult {
+        if let Err(ref error) = result {
+            log!("Batch item {} failed: {}", ethereum_order_hash, error);
+        }
+        BatchItemResult {
+            ethereum_order_hash,
+            success: result.is_ok(),
+            error: result.err(),
+        }
     }
 
     pub fn get_contract_balance(&self) -> NearToken {
         env::account_balance()
     }
-}
\ No newline at end of file
+}
+
+impl FusionEscrow {
+    /// Fresh state owned by `owner`, shared by `new` and state migrations
+    pub(crate) fn init(owner: AccountId) -> Self {
+        let mut contract = Self {
+            orders: IterableMap::new(StorageKey::Orders),
+            indexes: OrderIndexes::new(),
+            owner,
+            pending_owner: None,
+            roles: LookupMap::new(StorageKey::Roles),
+            authorized_resolvers: IterableMap::new(StorageKey::Resolvers),
+            bonds: LookupMap::new(StorageKey::Bonds),
+            bond_config: BondConfig::default(),
+            chains: IterableMap::new(StorageKey::Chains),
+            failed_payouts: LookupMap::new(StorageKey::FailedPayouts),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances),
+            min_order_amounts: LookupMap::new(StorageKey::MinOrderAmounts),
+            archived_orders: LookupMap::new(StorageKey::ArchivedOrders),
+            auctions: IterableMap::new(StorageKey::Auctions),
+            intents: IterableMap::new(StorageKey::Intents),
+            next_intent_id: 0,
+            paused_methods: Vec::new(),
+            claim_pause_epoch: 0,
+            resolver_stats: LookupMap::new(StorageKey::ResolverStats),
+            auto_suspend: None,
+            asset_totals: IterableMap::new(StorageKey::AssetTotals),
+            order_counts: OrderCounts::default(),
+            locked_safety_deposits: 0,
+            total_bonds: 0,
+            total_storage_balances: 0,
+            default_resolver_limits: ResolverLimits::default(),
+            resolver_limits: LookupMap::new(StorageKey::ResolverLimits),
+            resolver_usage: LookupMap::new(StorageKey::ResolverUsage),
+            accepted_tokens: IterableSet::new(StorageKey::AcceptedTokens),
+            migration: None,
+        };
+        
+        for chain in Self::default_chains() {
+            contract.chains.insert(chain.chain_id, chain);
+        }
+        contract
+    }
+}
+
+/// Index range for a page of `len` items, capped at `MAX_PAGE_SIZE`
+pub(crate) fn page_range(len: u64, from_index: Option<u64>, limit: Option<u64>) -> std::ops::Range<u64> {
+    let from_index = from_index.unwrap_or(0).min(len);
+    let limit = limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    from_index..std::cmp::min(from_index.saturating_add(limit), len)
+}
+
+/// Skip to `from_index` and take at most `limit` items of an iterator over `len` items.
+/// `Skip` calls `nth` once, which the `near_sdk::store` iterators answer by index without
+/// reading the skipped entries, so pass them unwrapped to keep a page's cost independent
+/// of `from_index`.
+pub(crate) fn page<I: Iterator>(iter: I, len: u32, from_index: Option<u64>, limit: Option<u64>) -> std::iter::Take<std::iter::Skip<I>> {
+    let range = page_range(u64::from(len), from_index, limit);
+    iter.skip(range.start as usize).take((range.end - range.start) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_batch_claim_reserves_ft_payout_gas() {
+        let mut contract = setup();
+        ft_order(&mut contract, 1, 1_000, 3_600);
+        ft_order(&mut contract, 2, 1_000, 3_600);
+
+        // Each claim with one token payout needs 45 Tgas, so only the first fits
+        call_with_gas(maker(), NearToken::from_yoctonear(0), NS, Gas::from_tgas(75));
+        let results = contract.batch_claim_with_secret(vec![
+            BatchClaim { ethereum_order_hash: order_hash(1), secret: secret(1) },
+            BatchClaim { ethereum_order_hash: order_hash(2), secret: secret(2) },
+        ]);
+        assert!(results[0].success);
+        assert!(!results[1].success);
+        assert!(results[1].error.as_ref().unwrap().starts_with("Insufficient gas"));
+        assert!(!contract.get_order(order_hash(2)).unwrap().completed);
+    }
+
+    #[test]
+    fn test_public_claim_reserves_fee_payout_gas() {
+        let mut contract = setup();
+        ft_order(&mut contract, 1, 1_000, 3_600);
+        let public_phase = EXCLUSIVE_CLAIM_PERIOD_NS + NS;
+
+        // Payout and fee need 80 Tgas
+        call_with_gas(stranger(), NearToken::from_yoctonear(0), public_phase, Gas::from_tgas(75));
+        let claims = vec![BatchClaim { ethereum_order_hash: order_hash(1), secret: secret(1) }];
+        assert!(!contract.batch_claim_with_secret(claims)[0].success);
+
+        call_with_gas(stranger(), NearToken::from_yoctonear(0), public_phase, Gas::from_tgas(85));
+        let claims = vec![BatchClaim { ethereum_order_hash: order_hash(1), secret: secret(1) }];
+        assert!(contract.batch_claim_with_secret(claims)[0].success);
+    }
+
+    #[test]
+    fn test_batch_cancel_reserves_ft_payout_gas() {
+        let mut contract = setup();
+        ft_order(&mut contract, 1, 1_000, 60);
+
+        call_with_gas(resolver(), NearToken::from_yoctonear(0), 61 * NS, Gas::from_tgas(40));
+        assert!(!contract.batch_cancel_orders(vec![order_hash(1)])[0].success);
+        assert!(!contract.get_order(order_hash(1)).unwrap().cancelled);
+
+        call_with_gas(resolver(), NearToken::from_yoctonear(0), 61 * NS, Gas::from_tgas(50));
+        assert!(contract.batch_cancel_orders(vec![order_hash(1)])[0].success);
+    }
+
+    const DEADLINE_SECONDS: u64 = 600;
+    const EXPIRED: u64 = (DEADLINE_SECONDS + 1) * NS;
+
+    fn near(amount: u128) -> u128 {
+        NearToken::from_near(amount).as_yoctonear()
+    }
+
+    #[test]
+    fn test_funder_cancel_refunds_full_amount() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        call(maker(), NearToken::from_yoctonear(0), EXPIRED);
+        let _ = contract.cancel_order(order_hash(1));
+        assert_eq!(transfers(), vec![(maker(), near(2))]);
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert!(order.cancelled);
+        assert_eq!(order.settled_at, EXPIRED);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order not expired yet")]
+    fn test_cancel_before_deadline_rejected() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(maker(), NearToken::from_yoctonear(0), DEADLINE_SECONDS * NS);
+        let _ = contract.cancel_order(order_hash(1));
+    }
+
+    #[test]
+    fn test_public_cancel_after_grace_period_pays_reward() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        call(stranger(), NearToken::from_yoctonear(0), DEADLINE_SECONDS * NS + CANCELLATION_GRACE_PERIOD_NS);
+        let results = contract.batch_cancel_orders(vec![order_hash(1)]);
+        assert_eq!(results[0].error.as_deref(), Some("Only funder can cancel during grace period"));
+        assert!(transfers().is_empty());
+
+        call(stranger(), NearToken::from_yoctonear(0), EXPIRED + CANCELLATION_GRACE_PERIOD_NS);
+        let _ = contract.cancel_order(order_hash(1));
+        let reward = near(2) * PUBLIC_CANCEL_REWARD_BPS / 10_000;
+        assert_eq!(transfers(), vec![(maker(), near(2) - reward), (stranger(), reward)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order already cancelled")]
+    fn test_cancel_twice_rejected() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(maker(), NearToken::from_yoctonear(0), EXPIRED);
+        let _ = contract.cancel_order(order_hash(1));
+        let _ = contract.cancel_order(order_hash(1));
+    }
+
+    #[test]
+    fn test_claim_pays_beneficiary_of_each_direction() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        near_to_eth_order(&mut contract, 2, NearToken::from_near(3), DEADLINE_SECONDS);
+
+        // The resolver may finish an ETH->NEAR claim, the maker is still paid
+        call(resolver(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(transfers(), vec![(maker(), near(2))]);
+
+        call(resolver(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(2), secret(2));
+        assert_eq!(transfers(), vec![(resolver(), near(3))]);
+        let order = contract.get_order(order_hash(2)).unwrap();
+        assert!(order.completed);
+        assert_eq!(order.revealed_secret, Some(secret(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only beneficiary or resolver can claim during exclusive period")]
+    fn test_third_party_claim_rejected_during_exclusive_period() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(stranger(), NearToken::from_yoctonear(0), EXCLUSIVE_CLAIM_PERIOD_NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+    }
+
+    #[test]
+    fn test_public_claim_pays_fee_to_caller() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), 3_600);
+
+        call(stranger(), NearToken::from_yoctonear(0), EXCLUSIVE_CLAIM_PERIOD_NS + 1);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        let fee = near(2) * PUBLIC_CLAIM_FEE_BPS / 10_000;
+        assert_eq!(transfers(), vec![(maker(), near(2) - fee), (stranger(), fee)]);
+    }
+
+    #[test]
+    fn test_maker_claim_pays_no_public_fee() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), 3_600);
+
+        call(maker(), NearToken::from_yoctonear(0), EXCLUSIVE_CLAIM_PERIOD_NS + 1);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(transfers(), vec![(resolver(), near(2))]);
+    }
+
+    #[test]
+    fn test_claim_rejects_wrong_secret_and_expired_order() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let claims = vec![BatchClaim { ethereum_order_hash: order_hash(1), secret: secret(2) }];
+        assert_eq!(contract.batch_claim_with_secret(claims)[0].error.as_deref(), Some("Invalid secret"));
+
+        call(maker(), NearToken::from_yoctonear(0), EXPIRED);
+        let claims = vec![BatchClaim { ethereum_order_hash: order_hash(1), secret: secret(1) }];
+        assert_eq!(contract.batch_claim_with_secret(claims)[0].error.as_deref(), Some("Order expired"));
+        assert!(transfers().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline exceeds maximum of 604800 seconds")]
+    fn test_deadline_past_maximum_rejected() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), u64::MAX);
+    }
+
+    #[test]
+    fn test_deadline_at_maximum_accepted() {
+        let mut contract = setup();
+        near_to_eth_order(&mut contract, 1, NearToken::from_near(2), MAX_DEADLINE_SECONDS);
+        assert_eq!(contract.get_order(order_hash(1)).unwrap().deadline, MAX_DEADLINE_SECONDS * NS);
+    }
+
+    const SAFETY_DEPOSIT: u128 = 1_000;
+
+    /// NEAR->ETH order `n` of 2 NEAR by `maker()` with `SAFETY_DEPOSIT` charged to its storage balance
+    fn order_with_safety_deposit(contract: &mut FusionEscrow, n: u8, deadline_seconds: u64) {
+        prepay_storage(contract, maker());
+        call(maker(), NearToken::from_near(2), 0);
+        contract.create_near_to_eth_order(
+            order_hash(n), resolver(), hashlock(n), None, deadline_seconds, None, Some(U128(SAFETY_DEPOSIT)), None,
+        );
+    }
+
+    #[test]
+    fn test_party_claim_returns_safety_deposit() {
+        let mut contract = setup();
+        order_with_safety_deposit(&mut contract, 1, DEADLINE_SECONDS);
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.amount.0, near(2));
+        assert_eq!(order.safety_deposit, U128(SAFETY_DEPOSIT));
+
+        call(resolver(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(transfers(), vec![(resolver(), near(2)), (maker(), SAFETY_DEPOSIT)]);
+    }
+
+    #[test]
+    fn test_public_claim_pays_safety_deposit_to_caller() {
+        let mut contract = setup();
+        order_with_safety_deposit(&mut contract, 1, 3_600);
+
+        // The deposit replaces the public fee, the resolver gets the full amount
+        call(stranger(), NearToken::from_yoctonear(0), EXCLUSIVE_CLAIM_PERIOD_NS + 1);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        assert_eq!(transfers(), vec![(resolver(), near(2)), (stranger(), SAFETY_DEPOSIT)]);
+    }
+
+    #[test]
+    fn test_funder_cancel_returns_safety_deposit() {
+        let mut contract = setup();
+        order_with_safety_deposit(&mut contract, 1, DEADLINE_SECONDS);
+
+        call(maker(), NearToken::from_yoctonear(0), EXPIRED);
+        let _ = contract.cancel_order(order_hash(1));
+        assert_eq!(transfers(), vec![(maker(), near(2)), (maker(), SAFETY_DEPOSIT)]);
+    }
+
+    #[test]
+    fn test_public_cancel_pays_safety_deposit_to_caller() {
+        let mut contract = setup();
+        order_with_safety_deposit(&mut contract, 1, DEADLINE_SECONDS);
+
+        call(stranger(), NearToken::from_yoctonear(0), EXPIRED + CANCELLATION_GRACE_PERIOD_NS);
+        // The deposit replaces the public reward, the maker is refunded the full amount
+        let _ = contract.cancel_order(order_hash(1));
+        assert_eq!(transfers(), vec![(maker(), near(2)), (stranger(), SAFETY_DEPOSIT)]);
+    }
+}
@@ -0,0 +1,383 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId};
+
+use crate::access::Role;
+use crate::{Asset, AssetAmount, CrossChainOrder, FusionEscrow, FusionEscrowExt, OrderStatus, SwapDirection};
+
+/// Exposure caps for orders a resolver funds (eth_to_near). `None` and assets
+/// missing from `max_locked` are unlimited.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverLimits {
+    pub max_open_orders: Option<u64>,
+    pub max_locked: Vec<AssetAmount>,
+    pub max_orders_per_window: Option<u64>,
+    pub window_seconds: u64,
+}
+
+impl ResolverLimits {
+    fn max_locked(&self, asset: &Asset) -> Option<u128> {
+        self.max_locked.iter().find(|limit| limit.asset == *asset).map(|limit| limit.amount.0)
+    }
+}
+
+/// What a resolver currently has open, plus its order count in the current window
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverUsage {
+    pub open_orders: u64,
+    pub locked: Vec<AssetAmount>,
+    pub window_start: u64,
+    pub orders_in_window: u64,
+}
+
+impl ResolverUsage {
+    fn locked(&self, asset: &Asset) -> u128 {
+        self.locked.iter().find(|locked| locked.asset == *asset).map_or(0, |locked| locked.amount.0)
+    }
+
+    fn add_locked(&mut self, asset: &Asset, amount: u128) {
+        match self.locked.iter_mut().find(|locked| locked.asset == *asset) {
+            Some(locked) => locked.amount = U128(locked.amount.0 + amount),
+            None => self.locked.push(AssetAmount { asset: asset.clone(), amount: U128(amount) }),
+        }
+    }
+
+    fn sub_locked(&mut self, asset: &Asset, amount: u128) {
+        if let Some(locked) = self.locked.iter_mut().find(|locked| locked.asset == *asset) {
+            locked.amount = U128(locked.amount.0.saturating_sub(amount));
+        }
+        self.locked.retain(|locked| locked.amount.0 > 0);
+    }
+
+    /// Orders counted in the window that contains `now`
+    fn orders_in_window(&self, limits: &ResolverLimits, now: u64) -> u64 {
+        if now < self.window_end(limits) {
+            self.orders_in_window
+        } else {
+            0
+        }
+    }
+
+    fn window_end(&self, limits: &ResolverLimits) -> u64 {
+        self.window_start + limits.window_seconds * 1_000_000_000
+    }
+}
+
+/// Reason an order was rejected by the resolver limits, the panic message of the rejection as JSON
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", tag = "error", rename_all = "snake_case")]
+pub enum LimitError {
+    TooManyOpenOrders { limit: u64 },
+    LockedValueExceeded { asset: Asset, limit: U128, locked: U128 },
+    RateLimited { limit: u64, retry_after: u64 },
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyOpenOrders { limit } => write!(f, "Resolver limit: {} open orders reached", limit),
+            Self::LockedValueExceeded { asset, limit, locked } => write!(
+                f,
+                "Resolver limit: locked {} would exceed {} ({} locked)",
+                asset, limit.0, locked.0
+            ),
+            Self::RateLimited { limit, retry_after } => write!(
+                f,
+                "Resolver limit: {} orders per window reached, retry after {}",
+                limit, retry_after
+            ),
+        }
+    }
+}
+
+impl LimitError {
+    /// Reject the order with the error as JSON, in the shape `check_resolver_limits`
+    /// returns, so callers can parse the failure
+    fn reject(&self) -> ! {
+        env::panic_str(&near_sdk::serde_json::to_string(self).expect("Failed to serialize limit error"))
+    }
+}
+
+/// Limits in effect for a resolver and how much of them it uses
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverUtilization {
+    pub account_id: AccountId,
+    pub limits: ResolverLimits,
+    pub open_orders: u64,
+    pub locked: Vec<AssetAmount>,
+    pub orders_in_window: u64,
+    pub window_ends_at: u64,
+}
+
+#[near]
+impl FusionEscrow {
+    /// Limits for resolvers without their own
+    pub fn set_default_resolver_limits(&mut self, limits: ResolverLimits) {
+        self.assert_role(Role::Admin);
+        Self::assert_valid_limits(&limits);
+        log!("Default resolver limits set to {:?}", limits);
+        self.default_resolver_limits = limits;
+    }
+
+    /// Override the default limits for one resolver, or remove the override with `None`
+    pub fn set_resolver_limits(&mut self, resolver: AccountId, limits: Option<ResolverLimits>) {
+        self.assert_role(Role::Admin);
+        log!("Limits for {} set to {:?}", resolver, limits);
+        match limits {
+            Some(limits) => {
+                Self::assert_valid_limits(&limits);
+                self.resolver_limits.insert(resolver, limits);
+            }
+            None => {
+                self.resolver_limits.remove(&resolver);
+            }
+        }
+    }
+
+    pub fn get_default_resolver_limits(&self) -> ResolverLimits {
+        self.default_resolver_limits.clone()
+    }
+
+    pub fn get_resolver_utilization(&self, resolver: AccountId) -> ResolverUtilization {
+        let limits = self.limits_for(&resolver).clone();
+        let usage = self.resolver_usage.get(&resolver).cloned().unwrap_or_default();
+        ResolverUtilization {
+            account_id: resolver,
+            open_orders: usage.open_orders,
+            locked: usage.locked.clone(),
+            orders_in_window: usage.orders_in_window(&limits, env::block_timestamp()),
+            window_ends_at: usage.window_end(&limits),
+            limits,
+        }
+    }
+
+    /// Limit that would reject an eth_to_near order of `amount` by `resolver` right now, if any
+    pub fn check_resolver_limits(&self, resolver: AccountId, asset: Asset, amount: U128) -> Option<LimitError> {
+        self.check_limits(&resolver, &asset, amount.0).err()
+    }
+}
+
+impl FusionEscrow {
+    fn assert_valid_limits(limits: &ResolverLimits) {
+        require!(
+            limits.max_orders_per_window.is_none() || limits.window_seconds > 0,
+            "Rate limit requires a window"
+        );
+    }
+
+    fn limits_for(&self, resolver: &AccountId) -> &ResolverLimits {
+        self.resolver_limits.get(resolver).unwrap_or(&self.default_resolver_limits)
+    }
+
+    fn check_limits(&self, resolver: &AccountId, asset: &Asset, amount: u128) -> Result<(), LimitError> {
+        let usage = self.resolver_usage.get(resolver).cloned().unwrap_or_default();
+        Self::check_usage(self.limits_for(resolver), &usage, asset, amount)
+    }
+
+    fn check_usage(limits: &ResolverLimits, usage: &ResolverUsage, asset: &Asset, amount: u128) -> Result<(), LimitError> {
+        if let Some(limit) = limits.max_open_orders {
+            if usage.open_orders >= limit {
+                return Err(LimitError::TooManyOpenOrders { limit });
+            }
+        }
+        if let Some(limit) = limits.max_locked(asset) {
+            let locked = usage.locked(asset);
+            if locked + amount > limit {
+                return Err(LimitError::LockedValueExceeded {
+                    asset: asset.clone(),
+                    limit: U128(limit),
+                    locked: U128(locked),
+                });
+            }
+        }
+        if let Some(limit) = limits.max_orders_per_window {
+            if usage.orders_in_window(limits, env::block_timestamp()) >= limit {
+                return Err(LimitError::RateLimited { limit, retry_after: usage.window_end(limits) });
+            }
+        }
+        Ok(())
+    }
+
+    /// Enforce the limits of the resolver funding `order` and count it towards them.
+    /// A violation panics with the `LimitError` as JSON.
+    pub(crate) fn reserve_resolver_capacity(&mut self, order: &CrossChainOrder) {
+        if order.direction != SwapDirection::EthToNear {
+            return;
+        }
+        if let Err(error) = self.check_limits(&order.resolver, &order.asset, order.amount.0) {
+            error.reject();
+        }
+
+        let now = env::block_timestamp();
+        let limits = self.limits_for(&order.resolver).clone();
+        let usage = self.resolver_usage.entry(order.resolver.clone()).or_default();
+        if now >= usage.window_end(&limits) {
+            usage.window_start = now;
+            usage.orders_in_window = 0;
+        }
+        usage.orders_in_window += 1;
+        usage.open_orders += 1;
+        usage.add_locked(&order.asset, order.amount.0);
+    }
+
+    /// Release the capacity of a settled order
+    pub(crate) fn release_resolver_capacity(&mut self, order: &CrossChainOrder) {
+        if order.direction != SwapDirection::EthToNear {
+            return;
+        }
+        if let Some(usage) = self.resolver_usage.get_mut(&order.resolver) {
+            usage.open_orders = usage.open_orders.saturating_sub(1);
+            usage.sub_locked(&order.asset, order.amount.0);
+        }
+    }
+
+    /// Count an open ETH->NEAR order against its resolver's usage
+    pub(crate) fn track_resolver_usage(&mut self, order: &CrossChainOrder) {
+        if order.direction == SwapDirection::EthToNear && order.status() == OrderStatus::Open {
+            let usage = self.resolver_usage.entry(order.resolver.clone()).or_default();
+            usage.open_orders += 1;
+            usage.add_locked(&order.asset, order.amount.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    const SECOND: u64 = 1_000_000_000;
+    const DEADLINE_SECONDS: u64 = 600;
+
+    fn limits() -> ResolverLimits {
+        ResolverLimits {
+            max_open_orders: Some(2),
+            max_locked: vec![AssetAmount { asset: Asset::Near, amount: U128(100) }],
+            max_orders_per_window: Some(3),
+            window_seconds: 60,
+        }
+    }
+
+    #[test]
+    fn test_open_orders_and_locked_value() {
+        testing_env!(VMContextBuilder::new().block_timestamp(10 * SECOND).build());
+        let mut usage = ResolverUsage::default();
+        assert_eq!(FusionEscrow::check_usage(&limits(), &usage, &Asset::Near, 100), Ok(()));
+        assert_eq!(
+            FusionEscrow::check_usage(&limits(), &usage, &Asset::Near, 101),
+            Err(LimitError::LockedValueExceeded { asset: Asset::Near, limit: U128(100), locked: U128(0) })
+        );
+
+        let token = Asset::Ft { token_id: "usdc.near".parse().unwrap() };
+        usage.open_orders = 1;
+        usage.add_locked(&Asset::Near, 60);
+        assert!(FusionEscrow::check_usage(&limits(), &usage, &Asset::Near, 50).is_err());
+        assert_eq!(FusionEscrow::check_usage(&limits(), &usage, &token, 1_000), Ok(()));
+
+        usage.open_orders = 2;
+        assert_eq!(
+            FusionEscrow::check_usage(&limits(), &usage, &token, 1),
+            Err(LimitError::TooManyOpenOrders { limit: 2 })
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_window() {
+        testing_env!(VMContextBuilder::new().block_timestamp(30 * SECOND).build());
+        let usage = ResolverUsage { window_start: 10 * SECOND, orders_in_window: 3, ..Default::default() };
+        assert_eq!(
+            FusionEscrow::check_usage(&limits(), &usage, &Asset::Near, 1),
+            Err(LimitError::RateLimited { limit: 3, retry_after: 70 * SECOND })
+        );
+
+        testing_env!(VMContextBuilder::new().block_timestamp(70 * SECOND).build());
+        assert_eq!(FusionEscrow::check_usage(&limits(), &usage, &Asset::Near, 1), Ok(()));
+    }
+
+    #[test]
+    fn test_rejection_is_typed_json() {
+        let error = LimitError::RateLimited { limit: 2, retry_after: 60 * SECOND };
+        let json = near_sdk::serde_json::to_string(&error).unwrap();
+        assert_eq!(json, r#"{"error":"rate_limited","limit":2,"retry_after":60000000000}"#);
+        assert_eq!(near_sdk::serde_json::from_str::<LimitError>(&json).unwrap(), error);
+    }
+
+    /// Contract with `limits` set for `resolver()`
+    fn limited(limits: ResolverLimits) -> FusionEscrow {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.set_resolver_limits(resolver(), Some(limits));
+        contract
+    }
+
+    fn near_limit(amount: NearToken) -> Vec<AssetAmount> {
+        vec![AssetAmount { asset: Asset::Near, amount: U128(amount.as_yoctonear()) }]
+    }
+
+    #[test]
+    #[should_panic(expected = "too_many_open_orders")]
+    fn test_order_past_open_orders_limit_rejected() {
+        let mut contract = limited(ResolverLimits { max_open_orders: Some(1), ..Default::default() });
+        near_order(&mut contract, 1, NearToken::from_near(1), DEADLINE_SECONDS);
+        near_order(&mut contract, 2, NearToken::from_near(1), DEADLINE_SECONDS);
+    }
+
+    #[test]
+    #[should_panic(expected = "locked_value_exceeded")]
+    fn test_order_past_locked_value_limit_rejected() {
+        let mut contract =
+            limited(ResolverLimits { max_locked: near_limit(NearToken::from_near(3)), ..Default::default() });
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        near_order(&mut contract, 2, NearToken::from_near(2), DEADLINE_SECONDS);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate_limited")]
+    fn test_order_past_rate_limit_rejected() {
+        let mut contract =
+            limited(ResolverLimits { max_orders_per_window: Some(2), window_seconds: 60, ..Default::default() });
+        near_order(&mut contract, 1, NearToken::from_near(1), DEADLINE_SECONDS);
+        near_order(&mut contract, 2, NearToken::from_near(1), DEADLINE_SECONDS);
+
+        // Settling orders frees capacity but not the window
+        call(maker(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        near_order(&mut contract, 3, NearToken::from_near(1), DEADLINE_SECONDS);
+    }
+
+    #[test]
+    fn test_usage_released_on_claim_and_cancel() {
+        let limits = ResolverLimits {
+            max_open_orders: Some(1),
+            max_locked: near_limit(NearToken::from_near(2)),
+            ..Default::default()
+        };
+        let mut contract = limited(limits);
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        let utilization = contract.get_resolver_utilization(resolver());
+        assert_eq!(utilization.open_orders, 1);
+        assert_eq!(utilization.locked, near_limit(NearToken::from_near(2)));
+        assert_eq!(
+            contract.check_resolver_limits(resolver(), Asset::Near, U128(1)),
+            Some(LimitError::TooManyOpenOrders { limit: 1 })
+        );
+
+        call(maker(), NearToken::from_yoctonear(0), 0);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        let utilization = contract.get_resolver_utilization(resolver());
+        assert_eq!(utilization.open_orders, 0);
+        assert!(utilization.locked.is_empty());
+
+        near_order(&mut contract, 2, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(resolver(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * SECOND);
+        let _ = contract.cancel_order(order_hash(2));
+        assert_eq!(contract.get_resolver_utilization(resolver()).open_orders, 0);
+        assert_eq!(contract.check_resolver_limits(resolver(), Asset::Near, U128(NearToken::from_near(2).as_yoctonear())), None);
+    }
+}
@@ -0,0 +1,567 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, AccountId, Gas, NearToken, Promise, PromiseResult};
+use std::marker::PhantomData;
+
+use crate::{
+    Asset, Bytes32, CrossChainOrder, FusionEscrow, FusionEscrowExt, HashAlgorithm, OrderStatus, ResolverStatus,
+    SwapDirection, DEFAULT_COUNTERPARTY_CHAIN_ID,
+};
+
+/// Layout version of the contract state, stored under `STATE_VERSION_KEY`.
+/// Version 1 is the original layout, which predates the key.
+pub const STATE_VERSION: u16 = 2;
+
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+// Gas kept by `upgrade` itself and its callback; everything else goes to the `migrate` call
+const GAS_RESERVED_FOR_UPGRADE: Gas = Gas::from_tgas(40);
+const GAS_FOR_UPGRADE_CALLBACK: Gas = Gas::from_tgas(5);
+
+/// Order layout of state version 1
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct CrossChainOrderV1 {
+    pub ethereum_order_hash: String,
+    pub direction: String,
+    pub maker: AccountId,
+    pub resolver: AccountId,
+    pub amount: NearToken,
+    pub hashlock: String,
+    pub deadline: u64,
+    pub completed: bool,
+    pub cancelled: bool,
+    pub revealed_secret: Option<String>,
+}
+
+/// Contract state layout of version 1
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FusionEscrowV1 {
+    pub orders: LegacyUnorderedMap<String, CrossChainOrderV1>,
+    pub owner: AccountId,
+    pub authorized_resolvers: LegacyUnorderedMap<AccountId, bool>,
+}
+
+/// Storage layout of the `UnorderedMap` of the legacy `near_sdk::collections` that
+/// version 1 was built on: keys and values in two vectors indexed by a u64, and the
+/// index of each key under `key_index_prefix` followed by the key
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyUnorderedMap<K, V> {
+    key_index_prefix: Vec<u8>,
+    keys: LegacyVector,
+    values: LegacyVector,
+    #[borsh(skip)]
+    entries: PhantomData<(K, V)>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+struct LegacyVector {
+    len: u64,
+    prefix: Vec<u8>,
+}
+
+/// Version 1 orders that `migrate_batch` still has to move, under their original prefix
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MigrationProgress {
+    pub orders: LegacyUnorderedMap<String, CrossChainOrderV1>,
+}
+
+/// Entries a migration still has to move
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MigrationStatus {
+    pub orders: u64,
+}
+
+/// Version-tagged order as stored in `orders`, so later layouts can be added
+/// without rewriting existing entries. Version 1 orders were stored untagged and
+/// are converted by the migration.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub enum VersionedOrder {
+    V2(CrossChainOrder),
+}
+
+impl From<VersionedOrder> for CrossChainOrder {
+    fn from(order: VersionedOrder) -> Self {
+        match order {
+            VersionedOrder::V2(order) => order,
+        }
+    }
+}
+
+impl<K: BorshSerialize + BorshDeserialize, V: BorshDeserialize> LegacyUnorderedMap<K, V> {
+    fn len(&self) -> u64 {
+        self.keys.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        let key = borsh::to_vec(key).expect("Invalid legacy key");
+        env::storage_has_key(&[self.key_index_prefix.as_slice(), &key].concat())
+    }
+
+    /// Remove and return the last entry, which leaves the others where they are
+    fn pop(&mut self) -> Option<(K, V)> {
+        let key = self.keys.pop()?;
+        let value = self.values.pop().expect("Missing legacy value");
+        env::storage_remove(&[self.key_index_prefix.as_slice(), &key].concat());
+        Some((
+            K::try_from_slice(&key).expect("Invalid legacy key"),
+            V::try_from_slice(&value).expect("Invalid legacy value"),
+        ))
+    }
+}
+
+impl LegacyVector {
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        self.len = self.len.checked_sub(1)?;
+        let key = [self.prefix.as_slice(), &self.len.to_le_bytes()].concat();
+        let value = env::storage_read(&key).expect("Missing legacy entry");
+        env::storage_remove(&key);
+        Some(value)
+    }
+}
+
+impl CrossChainOrderV1 {
+    /// The order in the current layout, stored under `ethereum_order_hash`
+    fn into_order(self, ethereum_order_hash: Bytes32) -> CrossChainOrder {
+        let direction = if self.direction == "near_to_eth" {
+            SwapDirection::NearToEth
+        } else {
+            SwapDirection::EthToNear
+        };
+        let storage_payer = match direction {
+            SwapDirection::EthToNear => self.resolver.clone(),
+            SwapDirection::NearToEth => self.maker.clone(),
+        };
+        let settled = self.completed || self.cancelled;
+        CrossChainOrder {
+            ethereum_order_hash,
+            direction,
+            counterparty_chain_id: DEFAULT_COUNTERPARTY_CHAIN_ID,
+            maker: self.maker,
+            resolver: self.resolver,
+            asset: Asset::Near,
+            amount: U128(self.amount.as_yoctonear()),
+            // v1 hashlocks are not 32-byte hashes and can never match a secret,
+            // so open v1 orders can only be cancelled once expired, without slashing
+            hashlock: Bytes32::from_hex(&self.hashlock).unwrap_or(Bytes32([0u8; 32])),
+            hash_algorithm: HashAlgorithm::default(),
+            deadline: self.deadline,
+            exclusive_until: 0,
+            completed: self.completed,
+            cancelled: self.cancelled,
+            revealed_secret: self.revealed_secret.and_then(|secret| Bytes32::from_hex(&secret).ok()),
+            storage_payer,
+            storage_deposit: U128(0),
+            safety_deposit: U128(0),
+            auction_rate: None,
+            ethereum_order: None,
+            intent: None,
+            created_at: env::block_timestamp(),
+            settled_at: if settled { env::block_timestamp() } else { 0 },
+            claim_pause_epoch: 0,
+        }
+    }
+}
+
+#[near]
+impl FusionEscrow {
+    /// Deploy new contract code, passed as the raw call input, and migrate the
+    /// state. Only the owner, which can be a DAO account, can upgrade.
+    pub fn upgrade(&self) -> Promise {
+        self.assert_owner();
+        self.assert_not_migrating();
+        let code = env::input().expect("Missing contract code");
+        let gas = env::prepaid_gas()
+            .saturating_sub(env::used_gas())
+            .saturating_sub(GAS_RESERVED_FOR_UPGRADE);
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), Vec::new(), NearToken::from_yoctonear(0), gas)
+            .then(Self::ext(env::current_account_id()).with_static_gas(GAS_FOR_UPGRADE_CALLBACK).on_upgrade())
+    }
+
+    /// Report whether the deployment and `migrate` went through. A failed `migrate`
+    /// reverts the deployment, so the previous code and state stay in place.
+    #[private]
+    pub fn on_upgrade(&self) -> bool {
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                log!("Upgraded to state version {}", Self::state_version());
+                true
+            }
+            PromiseResult::Failed => {
+                log!("Upgrade failed, state version {} kept", Self::state_version());
+                false
+            }
+        }
+    }
+
+    /// Convert the stored state to the current layout. Called by `upgrade`. Only the
+    /// contract fields are converted here; the orders stay where they are until
+    /// `migrate_batch` has moved them.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let version = Self::state_version();
+        let contract = match version {
+            1 => Self::start_migration(),
+            STATE_VERSION => env::panic_str("State already migrated"),
+            version => env::panic_str(&format!("Unknown state version {}", version)),
+        };
+
+        Self::write_state_version();
+        log!("Migrated state v{} to v{}", version, STATE_VERSION);
+        contract
+    }
+
+    /// Move up to `limit` version 1 orders left by `migrate` to the current layout and
+    /// return whether the migration is complete. Anyone can call it; until it completes,
+    /// every method that changes state is rejected and the views only show what was
+    /// already moved.
+    pub fn migrate_batch(&mut self, limit: u32) -> bool {
+        let mut migration = self.migration.take().expect("No migration in progress");
+        for _ in 0..limit {
+            if !self.migrate_next_order(&mut migration) {
+                break;
+            }
+        }
+
+        if !migration.orders.is_empty() {
+            self.migration = Some(migration);
+            return false;
+        }
+        log!("Migration complete");
+        true
+    }
+
+    /// What a migration in progress still has to move, if any
+    pub fn get_migration_status(&self) -> Option<MigrationStatus> {
+        self.migration.as_ref().map(|migration| MigrationStatus { orders: migration.orders.len() })
+    }
+
+    pub fn get_state_version(&self) -> u16 {
+        Self::state_version()
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn state_version() -> u16 {
+        env::storage_read(STATE_VERSION_KEY).map_or(1, |bytes| {
+            u16::from_le_bytes(bytes.try_into().expect("Invalid state version"))
+        })
+    }
+
+    pub(crate) fn write_state_version() {
+        env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.to_le_bytes());
+    }
+
+    /// Take over the owner and turn the boolean resolver whitelist into resolver
+    /// statuses. The orders stay under their old prefix in `migration`. The original
+    /// layout had no bonds or storage balances, so their totals start at zero.
+    fn start_migration() -> Self {
+        let mut old: FusionEscrowV1 = env::state_read().expect("No v1 state to migrate");
+        let mut contract = Self::init(old.owner);
+        while let Some((resolver, authorized)) = old.authorized_resolvers.pop() {
+            if authorized {
+                contract.authorized_resolvers.insert(resolver, ResolverStatus::Active);
+            }
+        }
+        if !old.orders.is_empty() {
+            contract.migration = Some(MigrationProgress { orders: old.orders });
+        }
+        contract
+    }
+
+    /// Move the last version 1 order under its 32-byte hash and count it in the totals,
+    /// and open ones in the resolver stats and usage. Returns false once there is
+    /// nothing left.
+    fn migrate_next_order(&mut self, migration: &mut MigrationProgress) -> bool {
+        let Some((ethereum_order_hash, order)) = migration.orders.pop() else {
+            return false;
+        };
+        let key = self.unused_legacy_key(&ethereum_order_hash, |canonical| {
+            migration.orders.contains_key(&canonical.to_string())
+        });
+        let order = order.into_order(key);
+        self.save_order(&key, &order);
+
+        self.track_order_created(&order);
+        if order.status() == OrderStatus::Open {
+            self.record_order_created(&order);
+            self.track_resolver_usage(&order);
+        } else {
+            self.track_order_settled(&order);
+        }
+        true
+    }
+
+    /// Key for a version 1 hash string: its 32-byte value, or the sha256 of the string
+    /// when it does not parse, the value is already taken, or `pending` says the
+    /// canonical spelling of the value is still to be moved and should keep it. Hash
+    /// strings that are not lowercase 0x-prefixed hex are logged with their key, which
+    /// their funder uses to settle them.
+    fn unused_legacy_key(&self, ethereum_order_hash: &str, pending: impl Fn(&str) -> bool) -> Bytes32 {
+        let key = legacy_order_key(ethereum_order_hash);
+        let canonical_pending = !is_canonical_order_hash(ethereum_order_hash) && pending(&key.to_hex());
+        let key = if canonical_pending || self.is_order_hash_taken(&key) { string_key(ethereum_order_hash) } else { key };
+        if !is_canonical_order_hash(ethereum_order_hash) {
+            log!("Order {} stored as {}", ethereum_order_hash, key);
+        }
+        key
+    }
+}
+
+/// Value of a hash string from before hashes were validated, see `string_key` for
+/// strings that do not parse
+fn legacy_order_key(ethereum_order_hash: &str) -> Bytes32 {
+    Bytes32::from_hex(ethereum_order_hash).unwrap_or_else(|_| string_key(ethereum_order_hash))
+}
+
+fn string_key(ethereum_order_hash: &str) -> Bytes32 {
+    Bytes32(env::sha256_array(ethereum_order_hash.as_bytes()))
+}
+
+fn is_canonical_order_hash(ethereum_order_hash: &str) -> bool {
+    Bytes32::from_hex(ethereum_order_hash).is_ok_and(|hash| hash.to_hex() == ethereum_order_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::transfers;
+    use crate::{OrderCounts, OrderStatus, ORDER_RETENTION_PERIOD_NS};
+    use near_sdk::collections::UnorderedMap;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    /// Version 1 state as the original contract wrote it, with the legacy collections
+    #[derive(BorshDeserialize, BorshSerialize)]
+    struct OriginalState {
+        orders: UnorderedMap<String, CrossChainOrderV1>,
+        owner: AccountId,
+        authorized_resolvers: UnorderedMap<AccountId, bool>,
+    }
+
+    /// Fresh 300 Tgas call context on the same storage
+    fn new_call() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).block_timestamp(5_000).build());
+    }
+
+    fn hash(n: u8) -> String {
+        Bytes32([n; 32]).to_hex()
+    }
+
+    fn v1_order(hash: &str, direction: &str, completed: bool, revealed_secret: Option<String>) -> CrossChainOrderV1 {
+        CrossChainOrderV1 {
+            ethereum_order_hash: hash.to_string(),
+            direction: direction.to_string(),
+            maker: accounts(1),
+            resolver: accounts(2),
+            amount: NearToken::from_near(1),
+            hashlock: "9f86d081884c7d65".to_string(),
+            deadline: 1_000,
+            completed,
+            cancelled: false,
+            revealed_secret,
+        }
+    }
+
+    /// Write a version 1 state with `orders` the way the original contract stored it
+    fn write_v1_state(orders: Vec<CrossChainOrderV1>) {
+        let mut state = OriginalState {
+            orders: UnorderedMap::new(b"o"),
+            owner: accounts(0),
+            authorized_resolvers: UnorderedMap::new(b"r"),
+        };
+        for (n, order) in orders.iter().enumerate() {
+            // Seed in chunks so no single call runs out of gas
+            if n % 50 == 0 {
+                env::state_write(&state);
+                new_call();
+                state = env::state_read().unwrap();
+            }
+            state.orders.insert(&order.ethereum_order_hash, order);
+        }
+        state.authorized_resolvers.insert(&accounts(2), &true);
+        state.authorized_resolvers.insert(&accounts(3), &false);
+        env::state_write(&state);
+    }
+
+    /// Run `migrate` and enough `migrate_batch` calls to complete it
+    fn migrate_all() -> FusionEscrow {
+        let mut contract = FusionEscrow::migrate();
+        while contract.migration.is_some() && !contract.migrate_batch(10) {}
+        contract
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        new_call();
+        write_v1_state(vec![
+            v1_order(&hash(1), "eth_to_near", false, None),
+            v1_order("0x02", "near_to_eth", true, Some("s3cret".to_string())),
+        ]);
+        assert_eq!(FusionEscrow::state_version(), 1);
+
+        let mut contract = FusionEscrow::migrate();
+        assert_eq!(FusionEscrow::state_version(), STATE_VERSION);
+        assert_eq!(contract.owner, accounts(0));
+        assert_eq!(contract.get_migration_status(), Some(MigrationStatus { orders: 2 }));
+        assert!(contract.migrate_batch(10));
+        assert_eq!(contract.get_migration_status(), None);
+
+        let open = contract.get_order(Bytes32([1; 32])).unwrap();
+        assert_eq!(open.direction, SwapDirection::EthToNear);
+        assert_eq!(open.amount.0, NearToken::from_near(1).as_yoctonear());
+        assert_eq!(open.storage_payer, accounts(2));
+        assert!(open.hashlock.is_zero());
+        assert_eq!(open.settled_at, 0);
+
+        // Hashes that are not 32 bytes of hex are keyed by their sha256
+        let completed = contract.get_order(string_key("0x02")).unwrap();
+        assert_eq!(completed.ethereum_order_hash, string_key("0x02"));
+        assert_eq!(completed.direction, SwapDirection::NearToEth);
+        assert_eq!(completed.storage_payer, accounts(1));
+        assert_eq!(completed.revealed_secret, None);
+        assert_eq!(completed.settled_at, 5_000);
+
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Open)), 1);
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Completed)), 1);
+        assert_eq!(contract.get_orders_by_maker(accounts(1), None, None).len(), 2);
+        assert_eq!(contract.get_order_counts(), OrderCounts { open: 1, completed: 1, cancelled: 0 });
+        let locked = contract.get_asset_totals(Asset::Near, SwapDirection::EthToNear).locked;
+        assert_eq!(locked.0, NearToken::from_near(1).as_yoctonear());
+        let near_to_eth = contract.get_asset_totals(Asset::Near, SwapDirection::NearToEth);
+        assert_eq!(near_to_eth.locked.0, 0);
+        assert_eq!(near_to_eth.completed_volume.0, NearToken::from_near(1).as_yoctonear());
+        assert_eq!(contract.get_resolver_utilization(accounts(2)).open_orders, 1);
+        assert_eq!(contract.get_resolver_stats(accounts(2)).stats.orders_created, 1);
+        let balance = contract.check_balance_consistency();
+        assert_eq!((balance.resolver_bonds.0, balance.storage_balances.0), (0, 0));
+
+        assert_eq!(contract.get_resolver_status(accounts(2)), Some(ResolverStatus::Active));
+        assert_eq!(contract.get_resolver_status(accounts(3)), None);
+        assert_eq!(contract.get_resolvers_count(), 1);
+    }
+
+    #[test]
+    fn test_cancel_migrated_order_spares_resolver() {
+        new_call();
+        write_v1_state(vec![v1_order(&hash(1), "eth_to_near", false, None)]);
+        let mut contract = migrate_all();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(contract.get_bond_config().min_bond)
+            .build());
+        contract.deposit_bond();
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).block_timestamp(1_001).build());
+        let _ = contract.cancel_order(Bytes32([1; 32]));
+        assert_eq!(transfers(), vec![(accounts(2), NearToken::from_near(1).as_yoctonear())]);
+        assert_eq!(contract.get_bond(accounts(2)).unwrap().bonded, contract.get_bond_config().min_bond);
+        assert_eq!(contract.get_resolver_stats(accounts(2)).stats.orders_expired, 0);
+    }
+
+    #[test]
+    fn test_prune_migrated_order_refunds_nothing() {
+        new_call();
+        write_v1_state(vec![v1_order(&hash(1), "near_to_eth", true, Some("s3cret".to_string()))]);
+        let mut contract = migrate_all();
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(5_000 + ORDER_RETENTION_PERIOD_NS)
+            .build());
+        assert!(contract.prune_orders(vec![Bytes32([1; 32])])[0].success);
+        assert!(transfers().is_empty());
+        assert_eq!(contract.get_archived_order(Bytes32([1; 32])).unwrap().status, OrderStatus::Completed);
+    }
+
+    #[test]
+    fn test_migrate_rekeys_mixed_case_hash() {
+        new_call();
+        let mixed_case = format!("0x{}", "aB".repeat(32));
+        let upper = format!("0x{}", "CD".repeat(32));
+        write_v1_state(vec![
+            v1_order(&hash(0xab), "near_to_eth", false, None),
+            v1_order(&mixed_case, "near_to_eth", false, None),
+            v1_order(&upper, "near_to_eth", false, None),
+        ]);
+
+        let contract = migrate_all();
+        // The canonical copy keeps the hash value whichever is moved first, the
+        // mixed-case one moves aside
+        assert!(contract.get_order(Bytes32([0xab; 32])).is_some());
+        let moved = contract.get_order(string_key(&mixed_case)).unwrap();
+        assert_eq!(moved.ethereum_order_hash, string_key(&mixed_case));
+        // Without a canonical copy, a differently cased hash keeps its value
+        assert!(contract.get_order(Bytes32([0xcd; 32])).is_some());
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Open)), 3);
+    }
+
+    #[test]
+    fn test_migrate_many_orders_in_batches() {
+        let last: u8 = 250;
+        new_call();
+        write_v1_state(
+            (0..=last)
+                .map(|n| v1_order(&hash(n), if n % 2 == 0 { "near_to_eth" } else { "eth_to_near" }, n % 2 == 1, None))
+                .collect(),
+        );
+
+        new_call();
+        let mut contract = FusionEscrow::migrate();
+        assert_eq!(contract.get_migration_status().unwrap().orders, u64::from(last) + 1);
+        assert!(contract.orders.is_empty());
+        env::state_write(&contract);
+        // Every batch runs in its own call, within the gas of one transaction
+        loop {
+            new_call();
+            contract = env::state_read().unwrap();
+            let done = contract.migrate_batch(25);
+            env::state_write(&contract);
+            if done {
+                break;
+            }
+        }
+
+        assert_eq!(contract.get_migration_status(), None);
+        assert_eq!(contract.orders.len(), u32::from(last) + 1);
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Open)), 126);
+        assert_eq!(contract.get_orders_count(Some(OrderStatus::Completed)), 125);
+        assert_eq!(contract.get_orders_by_maker(accounts(1), None, Some(100)).len(), 100);
+        assert!(contract.get_order(Bytes32([250; 32])).is_some());
+    }
+
+    #[test]
+    fn test_migrate_without_orders_completes_at_once() {
+        new_call();
+        write_v1_state(Vec::new());
+        let contract = FusionEscrow::migrate();
+        assert_eq!(contract.get_migration_status(), None);
+        assert_eq!(contract.get_resolvers_count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Migration in progress")]
+    fn test_orders_locked_during_migration() {
+        new_call();
+        write_v1_state(vec![v1_order(&hash(1), "near_to_eth", false, None)]);
+        let mut contract = FusionEscrow::migrate();
+        contract.deposit_storage(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "State already migrated")]
+    fn test_migrate_twice_rejected() {
+        new_call();
+        let _ = FusionEscrow::new();
+        FusionEscrow::migrate();
+    }
+}
@@ -0,0 +1,480 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId, Promise};
+
+use crate::pause::PausableMethod;
+use crate::{
+    page, Asset, Bytes32, EthAddress, EthereumOrder, FusionEscrow, FusionEscrowExt, HashAlgorithm, OrderParams,
+    SwapDirection, MAX_DEADLINE_SECONDS,
+};
+
+/// Time a resolver holds an intent exclusively after claiming it
+pub const INTENT_LOCK_PERIOD_NS: u64 = 300 * 1_000_000_000;
+
+/// Time after its lock ends before the same resolver can claim an intent again,
+/// so one resolver cannot keep an intent away from others by re-locking it
+pub const INTENT_RECLAIM_COOLDOWN_NS: u64 = INTENT_LOCK_PERIOD_NS;
+
+/// What the maker wants on the destination chain. The maker picks the hashlock,
+/// so only the maker can reveal the secret, the longest deadline a resolver can
+/// give the order, and the safety deposit, which is charged to the maker when a
+/// resolver commits.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IntentParams {
+    pub dest_asset: EthAddress,
+    pub dest_address: EthAddress,
+    pub min_return: U128,
+    pub max_deadline_seconds: u64,
+    pub hashlock: Bytes32,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub safety_deposit: Option<U128>,
+    pub expiry_seconds: u64,
+}
+
+/// NEAR->ETH intent without a resolver yet. Authorized resolvers discover it through
+/// the order book views, claim it for `INTENT_LOCK_PERIOD_NS` and then commit to it
+/// with their Ethereum order, which turns it into a regular order.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OpenIntent {
+    pub intent_id: u64,
+    pub maker: AccountId,
+    pub asset: Asset,
+    pub amount: U128,
+    #[serde(flatten)]
+    pub terms: IntentTerms,
+    pub hashlock: Bytes32,
+    pub hash_algorithm: HashAlgorithm,
+    pub safety_deposit: U128,
+    pub expiry: u64,
+    pub resolver: Option<AccountId>, // Resolver holding the lock, if any
+    pub locked_until: u64,
+    pub storage_deposit: U128,       // Charged to the maker's storage balance, credited back on removal
+}
+
+/// Destination terms of an intent, kept on the order it turns into
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IntentTerms {
+    pub dest_asset: EthAddress,
+    pub dest_address: EthAddress,
+    pub min_return: U128,
+    pub max_deadline_seconds: u64,
+}
+
+impl IntentTerms {
+    /// Check that the resolver's Ethereum order pays the maker what the intent asks for
+    fn assert_satisfied_by(&self, ethereum_order: &EthereumOrder) {
+        require!(ethereum_order.taker == self.dest_address, "Ethereum order does not pay the destination address");
+        require!(ethereum_order.token == self.dest_asset, "Ethereum order does not pay the destination asset");
+        require!(ethereum_order.amount.0 >= self.min_return.0, "Ethereum order below min return");
+    }
+}
+
+impl OpenIntent {
+    pub fn is_locked(&self, timestamp: u64) -> bool {
+        self.resolver.is_some() && timestamp <= self.locked_until
+    }
+}
+
+#[near]
+impl FusionEscrow {
+    /// Publish an intent funded with the attached NEAR. Its storage, and the order storage
+    /// and safety deposit once a resolver commits, come from the maker's storage balance.
+    #[payable]
+    pub fn post_intent(&mut self, params: IntentParams) -> u64 {
+        let amount = env::attached_deposit().as_yoctonear();
+        self.create_intent(params, env::predecessor_account_id(), Asset::Near, amount)
+    }
+
+    /// Lock an intent for the caller. Fails while another resolver holds the lock, and
+    /// for the caller until `INTENT_RECLAIM_COOLDOWN_NS` after its previous lock ended.
+    pub fn claim_intent(&mut self, intent_id: u64) {
+        self.assert_not_paused(PausableMethod::Intent);
+        let resolver = env::predecessor_account_id();
+        require!(self.is_authorized_resolver(resolver.clone()), "Not authorized resolver");
+
+        let intent = self.intents.get_mut(&intent_id).expect("Intent does not exist");
+        let now = env::block_timestamp();
+        require!(now <= intent.expiry, "Intent expired");
+        require!(!intent.is_locked(now), "Intent locked by another resolver");
+        require!(
+            intent.resolver.as_ref() != Some(&resolver) || now > intent.locked_until + INTENT_RECLAIM_COOLDOWN_NS,
+            "Intent recently locked by caller"
+        );
+
+        intent.resolver = Some(resolver.clone());
+        intent.locked_until = std::cmp::min(now + INTENT_LOCK_PERIOD_NS, intent.expiry);
+        log!("Intent {} claimed by {} until {}", intent_id, resolver, intent.locked_until);
+    }
+
+    /// Commit to a claimed intent with the resolver's Ethereum order, which must pay the
+    /// maker's destination terms. The order takes the maker's hashlock and safety deposit.
+    pub fn commit_intent(
+        &mut self,
+        intent_id: u64,
+        ethereum_order_hash: Bytes32,
+        deadline_seconds: u64,
+        counterparty_chain_id: Option<u64>,
+        ethereum_order: EthereumOrder,
+    ) {
+        self.assert_not_paused(PausableMethod::Intent);
+        let resolver = env::predecessor_account_id();
+        let intent = self.intents.get(&intent_id).cloned().expect("Intent does not exist");
+        require!(
+            intent.resolver.as_ref() == Some(&resolver) && intent.is_locked(env::block_timestamp()),
+            "Intent not locked by caller"
+        );
+        require!(deadline_seconds <= intent.terms.max_deadline_seconds, "Deadline exceeds intent maximum");
+        intent.terms.assert_satisfied_by(&ethereum_order);
+        self.remove_intent(&intent);
+
+        log!(
+            "Intent {} committed as order {}: {} {} for {} of {} to {}",
+            intent_id, ethereum_order_hash, intent.amount.0, intent.asset,
+            ethereum_order.amount.0, intent.terms.dest_asset, intent.terms.dest_address
+        );
+        let params = OrderParams {
+            ethereum_order_hash,
+            hashlock: intent.hashlock,
+            hash_algorithm: Some(intent.hash_algorithm),
+            deadline_seconds,
+            counterparty_chain_id,
+            safety_deposit: Some(intent.safety_deposit),
+        };
+        self.create_order(
+            params,
+            SwapDirection::NearToEth,
+            intent.maker.clone(),
+            resolver,
+            intent.asset,
+            intent.amount.0,
+            intent.maker,
+            None,
+            Some(ethereum_order),
+            Some(intent.terms),
+        );
+    }
+
+    /// Withdraw an intent and refund the maker. Not possible while a resolver holds the lock.
+    pub fn cancel_intent(&mut self, intent_id: u64) -> Promise {
+        self.assert_not_migrating();
+        let intent = self.intents.get(&intent_id).cloned().expect("Intent does not exist");
+        require!(env::predecessor_account_id() == intent.maker, "Only maker can cancel intent");
+        require!(!intent.is_locked(env::block_timestamp()), "Intent locked by resolver");
+        self.remove_intent(&intent);
+
+        log!("Intent {} cancelled: Refunding {} {} to {}", intent_id, intent.amount.0, intent.asset, intent.maker);
+        self.transfer_asset(&intent.asset, intent.maker, intent.amount.0)
+    }
+
+    pub fn get_intent(&self, intent_id: u64) -> Option<OpenIntent> {
+        self.intents.get(&intent_id).cloned()
+    }
+
+    /// Paginated order book, including intents currently locked by a resolver
+    pub fn get_intents(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<OpenIntent> {
+        page(self.intents.values(), self.intents.len(), from_index, limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_intents_count(&self) -> u64 {
+        u64::from(self.intents.len())
+    }
+}
+
+impl FusionEscrow {
+    /// Shared posting path for NEAR-funded and token-funded intents
+    pub(crate) fn create_intent(&mut self, params: IntentParams, maker: AccountId, asset: Asset, amount: u128) -> u64 {
+        self.assert_not_paused(PausableMethod::Intent);
+        require!(amount >= self.min_order_amount(&asset), "Order amount below minimum");
+        require!(params.expiry_seconds > 0, "Expiry must be positive");
+        require!(
+            params.expiry_seconds <= MAX_DEADLINE_SECONDS && params.max_deadline_seconds <= MAX_DEADLINE_SECONDS,
+            format!("Intent times exceed maximum of {} seconds", MAX_DEADLINE_SECONDS)
+        );
+        require!(!params.hashlock.is_zero(), "Invalid hashlock");
+
+        let intent_id = self.next_intent_id;
+        self.next_intent_id += 1;
+        let mut intent = OpenIntent {
+            intent_id,
+            maker,
+            asset,
+            amount: U128(amount),
+            terms: IntentTerms {
+                dest_asset: params.dest_asset,
+                dest_address: params.dest_address,
+                min_return: params.min_return,
+                max_deadline_seconds: params.max_deadline_seconds,
+            },
+            hashlock: params.hashlock,
+            hash_algorithm: params.hash_algorithm.unwrap_or_default(),
+            safety_deposit: params.safety_deposit.unwrap_or(U128(0)),
+            expiry: env::block_timestamp() + params.expiry_seconds * 1_000_000_000,
+            resolver: None,
+            locked_until: 0,
+            storage_deposit: U128(0),
+        };
+
+        let initial_storage = env::storage_usage();
+        self.intents.insert(intent_id, intent.clone());
+        self.intents.flush();
+        let storage_cost = Self::storage_cost_since(initial_storage);
+        self.charge_storage_balance(&intent.maker, storage_cost);
+        intent.storage_deposit = U128(storage_cost);
+        self.intents.insert(intent_id, intent.clone());
+
+        self.lock_funds(&intent.asset, SwapDirection::NearToEth, amount);
+        log!("Intent {} posted: {} {} by {}", intent_id, amount, intent.asset, intent.maker);
+        intent_id
+    }
+
+    /// Drop an intent, crediting its storage back to the maker's storage balance
+    fn remove_intent(&mut self, intent: &OpenIntent) {
+        self.intents.remove(&intent.intent_id);
+        self.unlock_funds(&intent.asset, SwapDirection::NearToEth, intent.amount.0);
+        self.credit_storage_balance(&intent.maker, intent.storage_deposit.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::NearToken;
+
+    fn eth(n: u8) -> EthAddress {
+        EthAddress([n; 20])
+    }
+
+    fn params(safety_deposit: Option<U128>) -> IntentParams {
+        IntentParams {
+            dest_asset: eth(1),
+            dest_address: eth(2),
+            min_return: U128(1_000),
+            max_deadline_seconds: 600,
+            hashlock: hashlock(7),
+            hash_algorithm: None,
+            safety_deposit,
+            expiry_seconds: 3_600,
+        }
+    }
+
+    fn post(contract: &mut FusionEscrow) -> u64 {
+        prepay_storage(contract, maker());
+        call(maker(), NearToken::from_near(5), 0);
+        contract.post_intent(params(Some(U128(100))))
+    }
+
+    /// Intent over 1_000 of `token()` posted through `ft_transfer_call`
+    fn post_ft(contract: &mut FusionEscrow) -> u64 {
+        prepay_storage(contract, maker());
+        call(token(), NearToken::from_yoctonear(0), 0);
+        let mut msg = near_sdk::serde_json::to_value(params(None)).unwrap();
+        msg["action"] = "post_intent".into();
+        let _ = contract.ft_on_transfer(maker(), U128(1_000), msg.to_string());
+        contract.get_intents_count() - 1
+    }
+
+    /// Authorize and bond `stranger()` as a second resolver
+    fn second_resolver(contract: &mut FusionEscrow) -> AccountId {
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.authorize_resolver(stranger());
+        call(stranger(), contract.get_bond_config().min_bond, 0);
+        contract.deposit_bond();
+        stranger()
+    }
+
+    fn claim(contract: &mut FusionEscrow, resolver: AccountId, intent_id: u64, timestamp: u64) {
+        call(resolver, NearToken::from_yoctonear(0), timestamp);
+        contract.claim_intent(intent_id);
+    }
+
+    fn ethereum_order(taker: EthAddress, token: EthAddress, amount: u128) -> EthereumOrder {
+        EthereumOrder { maker: eth(9), taker, token, amount: U128(amount) }
+    }
+
+    fn commit(contract: &mut FusionEscrow, intent_id: u64, order: EthereumOrder) {
+        call(resolver(), NearToken::from_yoctonear(0), NS);
+        contract.claim_intent(intent_id);
+        contract.commit_intent(intent_id, order_hash(1), 600, None, order);
+    }
+
+    #[test]
+    fn test_commit_uses_maker_terms() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        commit(&mut contract, intent_id, ethereum_order(eth(2), eth(1), 1_500));
+
+        assert_eq!(contract.get_intents_count(), 0);
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.maker, maker());
+        assert_eq!(order.resolver, resolver());
+        assert_eq!(order.hashlock, hashlock(7));
+        assert_eq!(order.amount, U128(NearToken::from_near(5).as_yoctonear()));
+        assert_eq!(order.safety_deposit, U128(100));
+        assert_eq!(
+            order.intent,
+            Some(IntentTerms {
+                dest_asset: eth(1),
+                dest_address: eth(2),
+                min_return: U128(1_000),
+                max_deadline_seconds: 600,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline exceeds intent maximum")]
+    fn test_commit_rejects_deadline_past_maker_maximum() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        contract.commit_intent(intent_id, order_hash(1), 601, None, ethereum_order(eth(2), eth(1), 1_500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent times exceed maximum")]
+    fn test_post_rejects_expiry_past_maximum() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(maker(), NearToken::from_near(5), 0);
+        contract.post_intent(IntentParams { expiry_seconds: u64::MAX, ..params(None) });
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent locked by another resolver")]
+    fn test_claim_is_exclusive_while_locked() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        let other = second_resolver(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        claim(&mut contract, other, intent_id, NS + INTENT_LOCK_PERIOD_NS);
+    }
+
+    #[test]
+    fn test_expired_lock_can_be_claimed_by_another_resolver() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        let other = second_resolver(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        claim(&mut contract, other.clone(), intent_id, 2 * NS + INTENT_LOCK_PERIOD_NS);
+
+        let intent = contract.get_intent(intent_id).unwrap();
+        assert_eq!(intent.resolver, Some(other));
+        assert_eq!(intent.locked_until, 2 * NS + 2 * INTENT_LOCK_PERIOD_NS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent not locked by caller")]
+    fn test_commit_after_lock_expiry_rejected() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        call(resolver(), NearToken::from_yoctonear(0), 2 * NS + INTENT_LOCK_PERIOD_NS);
+        contract.commit_intent(intent_id, order_hash(1), 600, None, ethereum_order(eth(2), eth(1), 1_500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent recently locked by caller")]
+    fn test_reclaim_within_cooldown_rejected() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        claim(&mut contract, resolver(), intent_id, 2 * NS + INTENT_LOCK_PERIOD_NS);
+    }
+
+    #[test]
+    fn test_reclaim_after_cooldown_allowed() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        let after_cooldown = 2 * NS + INTENT_LOCK_PERIOD_NS + INTENT_RECLAIM_COOLDOWN_NS;
+        claim(&mut contract, resolver(), intent_id, after_cooldown);
+        assert!(contract.get_intent(intent_id).unwrap().is_locked(after_cooldown));
+    }
+
+    #[test]
+    #[should_panic(expected = "Intent locked by resolver")]
+    fn test_cancel_during_lock_rejected() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        call(maker(), NearToken::from_yoctonear(0), 2 * NS);
+        let _ = contract.cancel_intent(intent_id);
+    }
+
+    #[test]
+    fn test_cancel_after_lock_refunds_maker() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        claim(&mut contract, resolver(), intent_id, NS);
+        call(maker(), NearToken::from_yoctonear(0), 2 * NS + INTENT_LOCK_PERIOD_NS);
+        let _ = contract.cancel_intent(intent_id);
+
+        assert!(contract.get_intent(intent_id).is_none());
+        assert_eq!(transfers(), vec![(maker(), NearToken::from_near(5).as_yoctonear())]);
+        assert_eq!(contract.get_storage_balance(maker()), U128(NearToken::from_near(1).as_yoctonear()));
+    }
+
+    #[test]
+    fn test_ft_intent_commits_as_token_order() {
+        let mut contract = setup();
+        let intent_id = post_ft(&mut contract);
+        assert_eq!(contract.get_intent(intent_id).unwrap().asset, Asset::Ft { token_id: token() });
+        commit(&mut contract, intent_id, ethereum_order(eth(2), eth(1), 1_500));
+
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.asset, Asset::Ft { token_id: token() });
+        assert_eq!(order.amount, U128(1_000));
+        assert_eq!(order.safety_deposit, U128(0));
+    }
+
+    #[test]
+    fn test_ft_intent_cancel_refunds_tokens() {
+        let mut contract = setup();
+        let intent_id = post_ft(&mut contract);
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.cancel_intent(intent_id);
+        assert_eq!(ft_transfers(), vec![(token(), maker(), 1_000)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Safety deposit not supported for token transfers")]
+    fn test_ft_intent_with_safety_deposit_rejected() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(token(), NearToken::from_yoctonear(0), 0);
+        let mut msg = near_sdk::serde_json::to_value(params(Some(U128(100)))).unwrap();
+        msg["action"] = "post_intent".into();
+        let _ = contract.ft_on_transfer(maker(), U128(1_000), msg.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Ethereum order does not pay the destination address")]
+    fn test_commit_rejects_other_taker() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        commit(&mut contract, intent_id, ethereum_order(eth(3), eth(1), 1_500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ethereum order does not pay the destination asset")]
+    fn test_commit_rejects_other_token() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        commit(&mut contract, intent_id, ethereum_order(eth(2), eth(3), 1_500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ethereum order below min return")]
+    fn test_commit_rejects_low_return() {
+        let mut contract = setup();
+        let intent_id = post(&mut contract);
+        commit(&mut contract, intent_id, ethereum_order(eth(2), eth(1), 999));
+    }
+}
@@ -0,0 +1,152 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near, require};
+
+use crate::access::Role;
+use crate::events::FusionEvent;
+use crate::{FusionEscrow, FusionEscrowExt};
+
+/// Entry points the guardian can pause. Cancellations, refunds, pruning and
+/// withdrawals are never pausable, so users can always recover their funds; only
+/// a state migration in progress holds them back until `migrate_batch` completes.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PausableMethod {
+    CreateOrder,     // Every path that creates an order, including ft_transfer_call
+    Claim,           // claim_with_secret and batch_claim_with_secret; resolvers are not slashed
+                     // for orders that were open while claims were paused
+    Auction,         // create_near_to_eth_auction and take_auction
+    Intent,          // post_intent, claim_intent and commit_intent
+    DepositBond,
+}
+
+pub(crate) const MIGRATION_IN_PROGRESS: &str = "Migration in progress";
+
+pub const PAUSABLE_METHODS: [PausableMethod; 5] = [
+    PausableMethod::CreateOrder,
+    PausableMethod::Claim,
+    PausableMethod::Auction,
+    PausableMethod::Intent,
+    PausableMethod::DepositBond,
+];
+
+#[near]
+impl FusionEscrow {
+    /// Pause the given methods, or all pausable methods if omitted
+    pub fn pause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_role(Role::Guardian);
+        let by = env::predecessor_account_id();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if !self.paused_methods.contains(&method) {
+                self.paused_methods.push(method);
+                self.bump_claim_pause_epoch(method);
+                FusionEvent::Paused { method, by: &by }.emit();
+            }
+        }
+    }
+
+    /// Resume the given methods, or everything if omitted
+    pub fn unpause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_role(Role::Admin);
+        let by = env::predecessor_account_id();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if self.paused_methods.contains(&method) {
+                self.paused_methods.retain(|paused| *paused != method);
+                self.bump_claim_pause_epoch(method);
+                FusionEvent::Unpaused { method, by: &by }.emit();
+            }
+        }
+    }
+
+    pub fn get_paused_methods(&self) -> Vec<PausableMethod> {
+        self.paused_methods.clone()
+    }
+
+    pub fn is_paused(&self, method: PausableMethod) -> bool {
+        self.paused_methods.contains(&method)
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn assert_not_paused(&self, method: PausableMethod) {
+        self.assert_not_migrating();
+        require!(!self.is_paused(method), format!("Method paused: {:?}", method));
+    }
+
+    /// Orders and deposits cannot change while `migrate_batch` is still moving them
+    pub(crate) fn assert_not_migrating(&self) {
+        require!(self.migration.is_none(), MIGRATION_IN_PROGRESS);
+    }
+
+    /// Whether claims were paused at any point since `claim_pause_epoch` was recorded.
+    /// The epoch changes on every pause and unpause of `Claim`.
+    pub(crate) fn claims_paused_since(&self, claim_pause_epoch: u64) -> bool {
+        self.is_paused(PausableMethod::Claim) || self.claim_pause_epoch != claim_pause_epoch
+    }
+
+    fn bump_claim_pause_epoch(&mut self, method: PausableMethod) {
+        if method == PausableMethod::Claim {
+            self.claim_pause_epoch += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::FusionEscrow;
+    use near_sdk::NearToken;
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    /// ETH->NEAR order by the bonded resolver, then the given pause steps while it runs
+    fn expire_order(pause_steps: &[bool]) -> FusionEscrow {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        call(owner(), NearToken::from_yoctonear(0), NS);
+        for pause in pause_steps {
+            if *pause {
+                contract.pause(Some(vec![PausableMethod::Claim]));
+            } else {
+                contract.unpause(Some(vec![PausableMethod::Claim]));
+            }
+        }
+
+        call(resolver(), NearToken::from_yoctonear(0), (DEADLINE_SECONDS + 1) * NS);
+        let _ = contract.cancel_order(order_hash(1));
+        contract
+    }
+
+    fn bonded(contract: &FusionEscrow) -> NearToken {
+        contract.get_bond(resolver()).unwrap().bonded
+    }
+
+    #[test]
+    fn test_expired_order_slashes_resolver() {
+        let contract = expire_order(&[]);
+        assert!(bonded(&contract) < contract.get_bond_config().min_bond);
+    }
+
+    #[test]
+    fn test_ongoing_claim_pause_skips_slashing() {
+        let contract = expire_order(&[true]);
+        assert_eq!(bonded(&contract), contract.get_bond_config().min_bond);
+    }
+
+    #[test]
+    fn test_past_claim_pause_skips_slashing() {
+        let contract = expire_order(&[true, false]);
+        assert_eq!(bonded(&contract), contract.get_bond_config().min_bond);
+    }
+
+    #[test]
+    fn test_other_pauses_keep_claim_epoch() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.pause(Some(vec![PausableMethod::Auction]));
+        contract.unpause(None);
+        assert_eq!(contract.claim_pause_epoch, 0);
+    }
+}
@@ -0,0 +1,273 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId};
+
+use crate::access::Role;
+use crate::events::FusionEvent;
+use crate::{page, Asset, CrossChainOrder, FusionEscrow, FusionEscrowExt, ResolverStatus, SwapDirection};
+
+/// Amount of one asset
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetAmount {
+    pub asset: Asset,
+    pub amount: U128,
+}
+
+/// Per-resolver counters, updated on every order state transition. Orders still open
+/// when the migration took them over count as created then. Expiries only count for
+/// orders the resolver accepted, see `accepted_by_resolver`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverStats {
+    pub orders_created: u64,
+    pub orders_completed: u64,
+    pub orders_expired: u64,         // Accepted orders cancelled after the deadline passed unclaimed
+    pub volume_filled: Vec<AssetAmount>,
+    pub total_completion_time: u64,  // Sum of creation-to-completion times, in ns
+}
+
+impl ResolverStats {
+    /// Completed share of settled orders, `None` until an order settled
+    pub fn completion_rate_bps(&self) -> Option<u16> {
+        let settled = self.orders_completed + self.orders_expired;
+        (settled > 0).then(|| (self.orders_completed * 10_000 / settled) as u16)
+    }
+
+    pub fn average_completion_time(&self) -> Option<u64> {
+        (self.orders_completed > 0).then(|| self.total_completion_time / self.orders_completed)
+    }
+
+    fn add_volume(&mut self, asset: &Asset, amount: u128) {
+        match self.volume_filled.iter_mut().find(|volume| volume.asset == *asset) {
+            Some(volume) => volume.amount = U128(volume.amount.0 + amount),
+            None => self.volume_filled.push(AssetAmount { asset: asset.clone(), amount: U128(amount) }),
+        }
+    }
+}
+
+/// Resolver statistics with the derived rates
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverStatsView {
+    pub account_id: AccountId,
+    #[serde(flatten)]
+    pub stats: ResolverStats,
+    pub completion_rate_bps: Option<u16>,
+    pub average_completion_time: Option<u64>,
+}
+
+/// Suspend active resolvers whose completion rate drops below `min_completion_rate_bps`
+/// once at least `min_settled_orders` of their orders settled
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AutoSuspendConfig {
+    pub min_completion_rate_bps: u16,
+    pub min_settled_orders: u64,
+}
+
+#[near]
+impl FusionEscrow {
+    pub fn get_resolver_stats(&self, resolver: AccountId) -> ResolverStatsView {
+        self.resolver_stats_view(resolver)
+    }
+
+    /// Statistics of registered resolvers, in the order of `get_resolvers`
+    pub fn get_resolvers_stats(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<ResolverStatsView> {
+        page(self.authorized_resolvers.keys(), self.authorized_resolvers.len(), from_index, limit)
+            .map(|resolver| self.resolver_stats_view(resolver.clone()))
+            .collect()
+    }
+
+    /// Enable automatic suspension, or disable it with `None`
+    pub fn set_auto_suspend(&mut self, config: Option<AutoSuspendConfig>) {
+        self.assert_role(Role::Admin);
+        if let Some(config) = &config {
+            require!(config.min_completion_rate_bps <= 10_000, "Completion rate above 100%");
+        }
+        log!("Auto-suspend set to {:?}", config);
+        self.auto_suspend = config;
+    }
+
+    pub fn get_auto_suspend(&self) -> Option<AutoSuspendConfig> {
+        self.auto_suspend.clone()
+    }
+}
+
+impl FusionEscrow {
+    fn resolver_stats_view(&self, resolver: AccountId) -> ResolverStatsView {
+        let stats = self.resolver_stats.get(&resolver).cloned().unwrap_or_default();
+        ResolverStatsView {
+            completion_rate_bps: stats.completion_rate_bps(),
+            average_completion_time: stats.average_completion_time(),
+            account_id: resolver,
+            stats,
+        }
+    }
+
+    pub(crate) fn record_order_created(&mut self, order: &CrossChainOrder) {
+        self.update_resolver_stats(order, |stats| stats.orders_created += 1);
+    }
+
+    pub(crate) fn record_order_completed(&mut self, order: &CrossChainOrder) {
+        self.update_resolver_stats(order, |stats| {
+            stats.orders_completed += 1;
+            stats.total_completion_time += order.settled_at.saturating_sub(order.created_at);
+            stats.add_volume(&order.asset, order.amount.0);
+        });
+    }
+
+    pub(crate) fn record_order_expired(&mut self, order: &CrossChainOrder) {
+        if !accepted_by_resolver(order) {
+            return;
+        }
+        self.update_resolver_stats(order, |stats| stats.orders_expired += 1);
+        self.check_auto_suspend(&order.resolver);
+    }
+
+    fn update_resolver_stats(&mut self, order: &CrossChainOrder, update: impl FnOnce(&mut ResolverStats)) {
+        let stats = self.resolver_stats.entry(order.resolver.clone()).or_default();
+        update(stats);
+    }
+
+    fn check_auto_suspend(&mut self, resolver: &AccountId) {
+        let Some(config) = &self.auto_suspend else {
+            return;
+        };
+        let Some(stats) = self.resolver_stats.get(resolver) else {
+            return;
+        };
+        let below_threshold = stats.orders_completed + stats.orders_expired >= config.min_settled_orders
+            && stats.completion_rate_bps().is_some_and(|rate| rate < config.min_completion_rate_bps);
+        if !below_threshold || self.authorized_resolvers.get(resolver) != Some(&ResolverStatus::Active) {
+            return;
+        }
+
+        self.authorized_resolvers.insert(resolver.clone(), ResolverStatus::Suspended);
+        FusionEvent::ResolverStatusChanged {
+            resolver,
+            status: Some(ResolverStatus::Suspended),
+            by: &env::current_account_id(),
+        }
+        .emit();
+    }
+}
+
+/// Whether the resolver took the order on itself: it funded it, committed to the intent
+/// it came from or took it from an auction. Makers can name any resolver on a plain
+/// NEAR->ETH order, so letting those expire must not count against it.
+fn accepted_by_resolver(order: &CrossChainOrder) -> bool {
+    order.direction == SwapDirection::EthToNear || order.intent.is_some() || order.auction_rate.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::NearToken;
+
+    const DEADLINE_SECONDS: u64 = 600;
+    const CREATED: u64 = NS;
+    const EXPIRED: u64 = CREATED + (DEADLINE_SECONDS + 1) * NS;
+
+    /// ETH->NEAR order `n` funded by `resolver()` with 2 NEAR
+    fn counted_order(contract: &mut FusionEscrow, n: u8) {
+        prepay_storage(contract, resolver());
+        call(resolver(), NearToken::from_near(2), CREATED);
+        contract.create_eth_to_near_order(order_hash(n), maker(), hashlock(n), None, DEADLINE_SECONDS, None, None, None);
+    }
+
+    fn expire(contract: &mut FusionEscrow, n: u8, funder: AccountId) {
+        call(funder, NearToken::from_yoctonear(0), EXPIRED);
+        let _ = contract.cancel_order(order_hash(n));
+    }
+
+    fn stats(contract: &FusionEscrow) -> ResolverStatsView {
+        contract.get_resolver_stats(resolver())
+    }
+
+    #[test]
+    fn test_rates() {
+        let mut stats = ResolverStats::default();
+        assert_eq!(stats.completion_rate_bps(), None);
+        assert_eq!(stats.average_completion_time(), None);
+
+        stats.orders_completed = 3;
+        stats.orders_expired = 1;
+        stats.total_completion_time = 90;
+        assert_eq!(stats.completion_rate_bps(), Some(7_500));
+        assert_eq!(stats.average_completion_time(), Some(30));
+    }
+
+    #[test]
+    fn test_volume_per_asset() {
+        let token = Asset::Ft { token_id: "usdc.near".parse().unwrap() };
+        let mut stats = ResolverStats::default();
+        stats.add_volume(&Asset::Near, 5);
+        stats.add_volume(&token, 7);
+        stats.add_volume(&Asset::Near, 10);
+        assert_eq!(
+            stats.volume_filled,
+            vec![
+                AssetAmount { asset: Asset::Near, amount: U128(15) },
+                AssetAmount { asset: token, amount: U128(7) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counters_follow_order_transitions() {
+        let mut contract = setup();
+        counted_order(&mut contract, 1);
+        counted_order(&mut contract, 2);
+        assert_eq!(stats(&contract).stats.orders_created, 2);
+
+        call(maker(), NearToken::from_yoctonear(0), CREATED + 10 * NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+        let view = stats(&contract);
+        assert_eq!(view.stats.orders_completed, 1);
+        assert_eq!(view.stats.volume_filled, vec![AssetAmount { asset: Asset::Near, amount: U128(NearToken::from_near(2).as_yoctonear()) }]);
+        assert_eq!(view.average_completion_time, Some(10 * NS));
+        assert_eq!(view.completion_rate_bps, Some(10_000));
+
+        expire(&mut contract, 2, resolver());
+        let view = stats(&contract);
+        assert_eq!(view.stats.orders_expired, 1);
+        assert_eq!(view.completion_rate_bps, Some(5_000));
+    }
+
+    #[test]
+    fn test_expiry_of_unaccepted_order_not_counted() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(maker(), NearToken::from_near(2), CREATED);
+        contract.create_near_to_eth_order(order_hash(1), resolver(), hashlock(1), None, DEADLINE_SECONDS, None, None, None);
+
+        expire(&mut contract, 1, maker());
+        let view = stats(&contract);
+        assert_eq!(view.stats.orders_created, 1);
+        assert_eq!(view.stats.orders_expired, 0);
+        assert_eq!(view.completion_rate_bps, None);
+    }
+
+    #[test]
+    fn test_auto_suspend_below_completion_rate() {
+        let mut contract = setup();
+        call(owner(), NearToken::from_yoctonear(0), 0);
+        contract.set_auto_suspend(Some(AutoSuspendConfig { min_completion_rate_bps: 5_000, min_settled_orders: 2 }));
+        for n in 1..=3 {
+            counted_order(&mut contract, n);
+        }
+
+        // A single settled order is below the minimum sample
+        expire(&mut contract, 1, resolver());
+        assert_eq!(contract.get_resolver_status(resolver()), Some(ResolverStatus::Active));
+
+        call(maker(), NearToken::from_yoctonear(0), CREATED + 10 * NS);
+        let _ = contract.claim_with_secret(order_hash(2), secret(2));
+        expire(&mut contract, 3, resolver());
+        assert_eq!(stats(&contract).completion_rate_bps, Some(3_333));
+        assert_eq!(contract.get_resolver_status(resolver()), Some(ResolverStatus::Suspended));
+    }
+}
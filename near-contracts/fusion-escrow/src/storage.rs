@@ -0,0 +1,275 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near, require, AccountId, NearToken, Promise};
+
+use crate::access::Role;
+use crate::pause::MIGRATION_IN_PROGRESS;
+use crate::{Asset, BatchItemResult, Bytes32, FusionEscrow, FusionEscrowExt, OrderStatus};
+
+/// Minimum amount for NEAR-funded orders unless the admin configures another one
+pub const DEFAULT_MIN_NEAR_ORDER_AMOUNT: NearToken = NearToken::from_millinear(100);
+
+/// How long a settled order stays in `orders` before anyone can prune it
+pub const ORDER_RETENTION_PERIOD_NS: u64 = 7 * 24 * 3600 * 1_000_000_000;
+
+/// Compact record kept for pruned orders so their hash cannot be reused.
+/// The hash itself is the archive key.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ArchivedOrder {
+    pub status: OrderStatus,
+    pub secret_hash: Option<Bytes32>,    // Hashlock the revealed secret matched, if completed
+}
+
+#[near]
+impl FusionEscrow {
+    /// Prepay the storage and safety deposits of new orders, intents and auctions, which are
+    /// charged here rather than to the funds they lock. Credits `account_id` or the caller
+    /// and returns the new balance.
+    #[payable]
+    pub fn deposit_storage(&mut self, account_id: Option<AccountId>) -> U128 {
+        self.assert_not_migrating();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let amount = env::attached_deposit().as_yoctonear();
+        require!(amount > 0, "Must attach NEAR tokens");
+
+        let balance = self.storage_balances.get(&account_id).copied().unwrap_or(0) + amount;
+        self.storage_balances.insert(account_id.clone(), balance);
+        self.total_storage_balances += amount;
+        log!("Storage deposit: {} yoctoNEAR for {}, balance {}", amount, account_id, balance);
+        U128(balance)
+    }
+
+    /// Withdraw unused prepaid storage, everything if `amount` is omitted
+    pub fn withdraw_storage(&mut self, amount: Option<U128>) -> Promise {
+        self.assert_not_migrating();
+        let account_id = env::predecessor_account_id();
+        let balance = self.storage_balances.get(&account_id).copied().unwrap_or(0);
+        let amount = amount.map(|a| a.0).unwrap_or(balance);
+        require!(amount > 0 && amount <= balance, "Invalid withdraw amount");
+
+        if amount == balance {
+            self.storage_balances.remove(&account_id);
+        } else {
+            self.storage_balances.insert(account_id.clone(), balance - amount);
+        }
+        self.total_storage_balances = self.total_storage_balances.saturating_sub(amount);
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(amount))
+    }
+
+    pub fn get_storage_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.storage_balances.get(&account_id).copied().unwrap_or(0))
+    }
+
+    pub fn set_min_order_amount(&mut self, asset: Asset, amount: U128) {
+        self.assert_role(Role::Admin);
+        require!(amount.0 > 0, "Minimum must be positive");
+        self.min_order_amounts.insert(asset.clone(), amount.0);
+        log!("Minimum order amount for {} set to {}", asset, amount.0);
+    }
+
+    pub fn get_min_order_amount(&self, asset: Asset) -> U128 {
+        U128(self.min_order_amount(&asset))
+    }
+
+    /// Remove settled orders older than the retention period, refunding their storage
+    /// deposit to whoever paid it. Anyone can call this; a compact archive entry is
+    /// kept per order so its hash is still rejected by `create_*_order`.
+    pub fn prune_orders(&mut self, ethereum_order_hashes: Vec<Bytes32>) -> Vec<BatchItemResult> {
+        ethereum_order_hashes
+            .into_iter()
+            .map(|ethereum_order_hash| {
+                let result = if Self::has_gas_for_batch_item() {
+                    self.try_prune(&ethereum_order_hash).map(|_| ())
+                } else {
+                    Err("Insufficient gas for batch item".to_string())
+                };
+                Self::batch_item_result(ethereum_order_hash, result)
+            })
+            .collect()
+    }
+
+    /// Archive entry of a pruned order
+    pub fn get_archived_order(&self, ethereum_order_hash: Bytes32) -> Option<ArchivedOrder> {
+        self.archived_orders.get(&ethereum_order_hash).cloned()
+    }
+}
+
+impl FusionEscrow {
+    pub(crate) fn min_order_amount(&self, asset: &Asset) -> u128 {
+        self.min_order_amounts.get(asset).copied().unwrap_or(match asset {
+            Asset::Near => DEFAULT_MIN_NEAR_ORDER_AMOUNT.as_yoctonear(),
+            Asset::Ft { .. } => 1,
+        })
+    }
+
+    /// Cost of the storage added since `initial_storage` was measured
+    pub(crate) fn storage_cost_since(initial_storage: u64) -> u128 {
+        let bytes = env::storage_usage().saturating_sub(initial_storage);
+        env::storage_byte_cost().as_yoctonear() * u128::from(bytes)
+    }
+
+    pub(crate) fn charge_storage_balance(&mut self, account_id: &AccountId, cost: u128) {
+        let balance = self.storage_balances.get(account_id).copied().unwrap_or(0);
+        require!(balance >= cost, "Insufficient storage balance, call deposit_storage");
+        self.storage_balances.insert(account_id.clone(), balance - cost);
+        self.total_storage_balances = self.total_storage_balances.saturating_sub(cost);
+    }
+
+    pub(crate) fn credit_storage_balance(&mut self, account_id: &AccountId, amount: u128) {
+        if amount > 0 {
+            let balance = self.storage_balances.get(account_id).copied().unwrap_or(0) + amount;
+            self.storage_balances.insert(account_id.clone(), balance);
+            self.total_storage_balances += amount;
+        }
+    }
+
+    /// Replace a settled order with its archive entry and refund the storage deposit,
+    /// minus what the archive entry itself occupies. Nothing is refunded when the archive
+    /// entry takes the whole deposit, as for orders migrated without one.
+    fn try_prune(&mut self, ethereum_order_hash: &Bytes32) -> Result<Option<Promise>, String> {
+        if self.migration.is_some() {
+            return Err(MIGRATION_IN_PROGRESS.to_string());
+        }
+        let order = self.load_order(ethereum_order_hash).ok_or("Order does not exist")?;
+        if order.settled_at == 0 {
+            return Err("Order not settled".to_string());
+        }
+        if env::block_timestamp() < order.settled_at + ORDER_RETENTION_PERIOD_NS {
+            return Err("Order still within retention period".to_string());
+        }
+
+        self.orders.remove(ethereum_order_hash);
+        self.indexes.remove(ethereum_order_hash, &order);
+
+        let archived = ArchivedOrder {
+            status: order.status(),
+            secret_hash: order.revealed_secret.map(|_| order.hashlock),
+        };
+        let initial_storage = env::storage_usage();
+        self.archived_orders.insert(*ethereum_order_hash, archived);
+        self.archived_orders.flush();
+        let refund = order.storage_deposit.0.saturating_sub(Self::storage_cost_since(initial_storage));
+
+        log!(
+            "Order {} pruned: Refunding {} yoctoNEAR storage to {}",
+            ethereum_order_hash, refund, order.storage_payer
+        );
+        if refund == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Promise::new(order.storage_payer).transfer(NearToken::from_yoctonear(refund))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const DEADLINE_SECONDS: u64 = 600;
+
+    fn storage_balance(contract: &FusionEscrow, account_id: AccountId) -> u128 {
+        contract.get_storage_balance(account_id).0
+    }
+
+    #[test]
+    fn test_near_order_charges_storage_balance() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.amount.0, NearToken::from_near(2).as_yoctonear());
+        assert_eq!(order.storage_payer, resolver());
+        // The first order also pays for the resolver stats, usage and asset totals
+        let charged = NearToken::from_near(1).as_yoctonear() - storage_balance(&contract, resolver());
+        assert!(order.storage_deposit.0 > 0 && charged > order.storage_deposit.0);
+    }
+
+    #[test]
+    fn test_safety_deposit_charged_to_storage_balance() {
+        let mut contract = setup();
+        prepay_storage(&mut contract, maker());
+        call(maker(), NearToken::from_near(2), 0);
+        let safety_deposit = U128(NearToken::from_millinear(100).as_yoctonear());
+        contract.create_near_to_eth_order(
+            order_hash(1), resolver(), hashlock(1), None, DEADLINE_SECONDS, None, Some(safety_deposit), None,
+        );
+
+        let order = contract.get_order(order_hash(1)).unwrap();
+        assert_eq!(order.amount.0, NearToken::from_near(2).as_yoctonear());
+        let charged = NearToken::from_near(1).as_yoctonear() - storage_balance(&contract, maker());
+        assert!(charged > order.storage_deposit.0 + safety_deposit.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient storage balance, call deposit_storage")]
+    fn test_near_order_requires_storage_balance() {
+        let mut contract = setup();
+        call(resolver(), NearToken::from_near(2), 0);
+        contract.create_eth_to_near_order(order_hash(1), maker(), hashlock(1), None, DEADLINE_SECONDS, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order amount below minimum")]
+    fn test_order_below_minimum_rejected() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_millinear(99), DEADLINE_SECONDS);
+    }
+
+    #[test]
+    fn test_prune_refunds_storage_deposit() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        let cancelled_at = (DEADLINE_SECONDS + 1) * NS;
+        call(resolver(), NearToken::from_yoctonear(0), cancelled_at);
+        let _ = contract.cancel_order(order_hash(1));
+        let storage_deposit = contract.get_order(order_hash(1)).unwrap().storage_deposit.0;
+
+        call(stranger(), NearToken::from_yoctonear(0), cancelled_at + ORDER_RETENTION_PERIOD_NS - 1);
+        let results = contract.prune_orders(vec![order_hash(1)]);
+        assert_eq!(results[0].error.as_deref(), Some("Order still within retention period"));
+
+        call(stranger(), NearToken::from_yoctonear(0), cancelled_at + ORDER_RETENTION_PERIOD_NS);
+        let results = contract.prune_orders(vec![order_hash(1)]);
+        assert!(results[0].success);
+        let refunds = transfers();
+        assert_eq!(refunds.len(), 1);
+        let (receiver, refund) = refunds[0].clone();
+        assert_eq!(receiver, resolver());
+        // The archive entry keeps part of the deposit
+        assert!(refund > 0 && refund < storage_deposit);
+
+        assert!(contract.get_order(order_hash(1)).is_none());
+        assert_eq!(contract.get_archived_order(order_hash(1)).unwrap().status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "Order already exists")]
+    fn test_pruned_completed_order_keeps_hash_taken() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(maker(), NearToken::from_yoctonear(0), NS);
+        let _ = contract.claim_with_secret(order_hash(1), secret(1));
+
+        call(stranger(), NearToken::from_yoctonear(0), NS + ORDER_RETENTION_PERIOD_NS);
+        assert!(contract.prune_orders(vec![order_hash(1)])[0].success);
+        let archived = contract.get_archived_order(order_hash(1)).unwrap();
+        assert_eq!(archived.status, OrderStatus::Completed);
+        assert_eq!(archived.secret_hash, Some(hashlock(1)));
+
+        call(resolver(), NearToken::from_near(2), NS + ORDER_RETENTION_PERIOD_NS);
+        contract.create_eth_to_near_order(order_hash(1), maker(), hashlock(2), None, DEADLINE_SECONDS, None, None, None);
+    }
+
+    #[test]
+    fn test_prune_rejects_open_order() {
+        let mut contract = setup();
+        near_order(&mut contract, 1, NearToken::from_near(2), DEADLINE_SECONDS);
+        call(stranger(), NearToken::from_yoctonear(0), ORDER_RETENTION_PERIOD_NS);
+        let results = contract.prune_orders(vec![order_hash(1)]);
+        assert_eq!(results[0].error.as_deref(), Some("Order not settled"));
+        assert!(transfers().is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Shared setup for the contract unit tests
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::mock::MockAction;
+use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+use near_sdk::{testing_env, AccountId, Gas, NearToken};
+
+use crate::{Bytes32, FusionEscrow, HashAlgorithm};
+
+pub const NS: u64 = 1_000_000_000;
+
+pub fn owner() -> AccountId {
+    accounts(0)
+}
+
+pub fn maker() -> AccountId {
+    accounts(1)
+}
+
+pub fn resolver() -> AccountId {
+    accounts(2)
+}
+
+pub fn stranger() -> AccountId {
+    accounts(3)
+}
+
+pub fn token() -> AccountId {
+    "usdc.near".parse().unwrap()
+}
+
+/// Set the caller, attached deposit and block time for the next call
+pub fn call(predecessor: AccountId, deposit: NearToken, timestamp: u64) {
+    call_with_gas(predecessor, deposit, timestamp, Gas::from_tgas(300));
+}
+
+pub fn call_with_gas(predecessor: AccountId, deposit: NearToken, timestamp: u64, gas: Gas) {
+    testing_env!(VMContextBuilder::new()
+        .predecessor_account_id(predecessor)
+        .attached_deposit(deposit)
+        .block_timestamp(timestamp)
+        .prepaid_gas(gas)
+        .build());
+}
+
+/// NEAR transfers scheduled by the last call, as `(receiver, yoctoNEAR)`
+pub fn transfers() -> Vec<(AccountId, u128)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let receiver_id = receipt.receiver_id;
+            receipt.actions.into_iter().filter_map(move |action| match action {
+                MockAction::Transfer { deposit, .. } => Some((receiver_id.clone(), deposit.as_yoctonear())),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// `ft_transfer` calls scheduled by the last call, as `(token, receiver, amount)`
+pub fn ft_transfers() -> Vec<(AccountId, AccountId, u128)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let token_id = receipt.receiver_id;
+            receipt.actions.into_iter().filter_map(move |action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"ft_transfer" => {
+                    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+                    let receiver_id = args["receiver_id"].as_str().unwrap().parse().unwrap();
+                    let amount = args["amount"].as_str().unwrap().parse().unwrap();
+                    Some((token_id.clone(), receiver_id, amount))
+                }
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Contract owned by `owner()` with `resolver()` authorized and bonded, accepting `token()`
+pub fn setup() -> FusionEscrow {
+    call(owner(), NearToken::from_yoctonear(0), 0);
+    let mut contract = FusionEscrow::new();
+    contract.add_accepted_token(token());
+    contract.authorize_resolver(resolver());
+    call(resolver(), contract.get_bond_config().min_bond, 0);
+    contract.deposit_bond();
+    contract
+}
+
+pub fn order_hash(n: u8) -> Bytes32 {
+    Bytes32([n; 32])
+}
+
+pub fn secret(n: u8) -> Bytes32 {
+    Bytes32([n; 32])
+}
+
+pub fn hashlock(n: u8) -> Bytes32 {
+    HashAlgorithm::Keccak256.hash(&secret(n).0)
+}
+
+/// Prepay 1 NEAR of storage for `account_id`
+pub fn prepay_storage(contract: &mut FusionEscrow, account_id: AccountId) {
+    call(account_id, NearToken::from_near(1), 0);
+    contract.deposit_storage(None);
+}
+
+/// ETH->NEAR order `n` funded by `resolver()` with `amount` of attached NEAR, with
+/// storage prepaid
+pub fn near_order(contract: &mut FusionEscrow, n: u8, amount: NearToken, deadline_seconds: u64) {
+    prepay_storage(contract, resolver());
+    call(resolver(), amount, 0);
+    contract.create_eth_to_near_order(order_hash(n), maker(), hashlock(n), None, deadline_seconds, None, None, None);
+}
+
+/// NEAR->ETH order `n` funded by `maker()` with `amount` of attached NEAR, with
+/// storage prepaid
+pub fn near_to_eth_order(contract: &mut FusionEscrow, n: u8, amount: NearToken, deadline_seconds: u64) {
+    prepay_storage(contract, maker());
+    call(maker(), amount, 0);
+    contract.create_near_to_eth_order(order_hash(n), resolver(), hashlock(n), None, deadline_seconds, None, None, None);
+}
+
+/// ETH->NEAR order `n` funded by `resolver()` with `amount` of `token()` through
+/// `ft_transfer_call`, with storage prepaid
+pub fn ft_order(contract: &mut FusionEscrow, n: u8, amount: u128, deadline_seconds: u64) {
+    prepay_storage(contract, resolver());
+    call(token(), NearToken::from_yoctonear(0), 0);
+    let msg = json!({
+        "action": "create_eth_to_near_order",
+        "maker": maker(),
+        "ethereum_order_hash": order_hash(n),
+        "hashlock": hashlock(n),
+        "deadline_seconds": deadline_seconds,
+    });
+    let _ = contract.ft_on_transfer(resolver(), U128(amount), msg.to_string());
+}
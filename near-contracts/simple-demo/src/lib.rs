@@ -1,12 +1,27 @@
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
 use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::store::IterableMap;
 use near_sdk::{
-    env, near_bindgen, AccountId, Balance, Promise, 
+    env, near, AccountId, BorshStorageKey, NearToken, Promise, 
     require, log, PanicOnDefault
 };
 
-#[derive(BorshDeserialize, BorshSerialize)]
+mod migration;
+
+pub use migration::{FusionDemoV1, STATE_VERSION};
+
+/// Storage prefixes. `Orders` keeps the prefix of the legacy map it replaced.
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum StorageKey {
+    Orders = b'o',
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
 pub struct Order {
     pub maker: AccountId,
     pub amount: U128,
@@ -14,24 +29,37 @@ pub struct Order {
     pub completed: bool,
 }
 
-#[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+/// Methods the owner or guardian can pause
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PausableMethod {
+    CreateOrder,
+    Claim,
+}
+
+const PAUSABLE_METHODS: [PausableMethod; 2] = [PausableMethod::CreateOrder, PausableMethod::Claim];
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
 pub struct FusionDemo {
-    orders: UnorderedMap<String, Order>,
+    orders: IterableMap<String, Order>,
+    owner: AccountId,
+    guardian: Option<AccountId>,
+    paused_methods: Vec<PausableMethod>,
 }
 
-#[near_bindgen]
+#[near]
 impl FusionDemo {
     #[init]
     pub fn new() -> Self {
-        Self {
-            orders: UnorderedMap::new(b"o"),
-        }
+        Self::write_state_version();
+        Self::init(IterableMap::new(StorageKey::Orders))
     }
     
     #[payable]
     pub fn create_order(&mut self, order_hash: String, hashlock: String) {
-        let amount = env::attached_deposit();
+        self.assert_not_paused(PausableMethod::CreateOrder);
+        let amount = env::attached_deposit().as_yoctonear();
         require!(amount > 0, "Must attach NEAR");
         
         let order = Order {
@@ -41,31 +69,177 @@ impl FusionDemo {
             completed: false,
         };
         
-        self.orders.insert(&order_hash, &order);
+        self.orders.insert(order_hash, order);
         log!("Order created: {} yoctoNEAR", amount);
     }
     
     pub fn claim(&mut self, order_hash: String, secret: String) -> Promise {
-        let mut order = self.orders.get(&order_hash).expect("Order not found");
+        self.assert_not_paused(PausableMethod::Claim);
+        let order = self.orders.get_mut(&order_hash).expect("Order not found");
         require!(!order.completed, "Already completed");
         
         // Simple validation - just check secret is not empty
         require!(!secret.is_empty(), "Secret required");
         
         order.completed = true;
-        self.orders.insert(&order_hash, &order);
         
-        let amount: Balance = order.amount.into();
-        log!("Claiming {} yoctoNEAR for {}", amount, env::predecessor_account_id());
+        let amount = NearToken::from_yoctonear(order.amount.0);
+        log!("Claiming {} yoctoNEAR for {}", amount.as_yoctonear(), env::predecessor_account_id());
         
         Promise::new(env::predecessor_account_id()).transfer(amount)
     }
     
     pub fn get_order(&self, order_hash: String) -> Option<Order> {
-        self.orders.get(&order_hash)
+        self.orders.get(&order_hash).cloned()
     }
     
     pub fn test(&self) -> String {
         "Contract is working!".to_string()
     }
-}
\ No newline at end of file
+    
+    /// Set the account allowed to pause (only by owner)
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        require!(env::predecessor_account_id() == self.owner, "Only owner can set guardian");
+        self.guardian = guardian;
+    }
+    
+    /// Pause the given methods, or all of them if omitted (owner or guardian)
+    pub fn pause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_owner_or_guardian();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if !self.paused_methods.contains(&method) {
+                self.paused_methods.push(method);
+                Self::emit_pause_event("paused", method);
+            }
+        }
+    }
+    
+    /// Resume the given methods, or all of them if omitted (owner or guardian)
+    pub fn unpause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_owner_or_guardian();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if self.paused_methods.contains(&method) {
+                self.paused_methods.retain(|paused| *paused != method);
+                Self::emit_pause_event("unpaused", method);
+            }
+        }
+    }
+    
+    pub fn get_paused_methods(&self) -> Vec<PausableMethod> {
+        self.paused_methods.clone()
+    }
+}
+
+impl FusionDemo {
+    /// State around `orders`, owned by the caller; shared by `new` and `migrate`
+    fn init(orders: IterableMap<String, Order>) -> Self {
+        Self {
+            orders,
+            owner: env::predecessor_account_id(),
+            guardian: None,
+            paused_methods: Vec::new(),
+        }
+    }
+    
+    fn assert_owner_or_guardian(&self) {
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == self.owner || self.guardian.as_ref() == Some(&caller),
+            "Only owner or guardian can pause"
+        );
+    }
+    
+    fn assert_not_paused(&self, method: PausableMethod) {
+        require!(!self.paused_methods.contains(&method), "Method paused");
+    }
+    
+    fn emit_pause_event(event: &str, method: PausableMethod) {
+        let event = json!({
+            "standard": "simple-fusion-demo",
+            "version": "1.0.0",
+            "event": event,
+            "data": { "method": method, "by": env::predecessor_account_id() },
+        });
+        log!("EVENT_JSON:{}", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn call(predecessor: AccountId, deposit: NearToken) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit)
+            .build());
+    }
+
+    #[test]
+    fn test_create_and_claim() {
+        call(accounts(0), NearToken::from_yoctonear(0));
+        let mut contract = FusionDemo::new();
+
+        call(accounts(1), NearToken::from_near(2));
+        contract.create_order("0x01".to_string(), "lock".to_string());
+        let order = contract.get_order("0x01".to_string()).unwrap();
+        assert_eq!(order.maker, accounts(1));
+        assert_eq!(order.amount, U128(NearToken::from_near(2).as_yoctonear()));
+        assert!(!order.completed);
+
+        call(accounts(2), NearToken::from_yoctonear(0));
+        let _ = contract.claim("0x01".to_string(), "secret".to_string());
+        assert!(contract.get_order("0x01".to_string()).unwrap().completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already completed")]
+    fn test_claim_twice_rejected() {
+        call(accounts(0), NearToken::from_yoctonear(0));
+        let mut contract = FusionDemo::new();
+        call(accounts(1), NearToken::from_near(1));
+        contract.create_order("0x01".to_string(), "lock".to_string());
+        let _ = contract.claim("0x01".to_string(), "secret".to_string());
+        let _ = contract.claim("0x01".to_string(), "secret".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Method paused")]
+    fn test_guardian_pauses_create_order() {
+        call(accounts(0), NearToken::from_yoctonear(0));
+        let mut contract = FusionDemo::new();
+        contract.set_guardian(Some(accounts(3)));
+
+        call(accounts(3), NearToken::from_yoctonear(0));
+        contract.pause(Some(vec![PausableMethod::CreateOrder]));
+        assert_eq!(contract.get_paused_methods(), vec![PausableMethod::CreateOrder]);
+
+        call(accounts(1), NearToken::from_near(1));
+        contract.create_order("0x01".to_string(), "lock".to_string());
+    }
+
+    #[test]
+    fn test_unpause_resumes_claims() {
+        call(accounts(0), NearToken::from_yoctonear(0));
+        let mut contract = FusionDemo::new();
+        call(accounts(1), NearToken::from_near(1));
+        contract.create_order("0x01".to_string(), "lock".to_string());
+
+        call(accounts(0), NearToken::from_yoctonear(0));
+        contract.pause(None);
+        contract.unpause(Some(vec![PausableMethod::Claim]));
+        assert_eq!(contract.get_paused_methods(), vec![PausableMethod::CreateOrder]);
+        let _ = contract.claim("0x01".to_string(), "secret".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner or guardian can pause")]
+    fn test_pause_requires_owner_or_guardian() {
+        call(accounts(0), NearToken::from_yoctonear(0));
+        let mut contract = FusionDemo::new();
+        call(accounts(1), NearToken::from_yoctonear(0));
+        contract.pause(None);
+    }
+}
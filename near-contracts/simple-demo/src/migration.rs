@@ -0,0 +1,149 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::store::IterableMap;
+use near_sdk::{env, log, near};
+
+use crate::{FusionDemo, FusionDemoExt, Order, StorageKey};
+
+/// Layout version of the contract state, stored under `STATE_VERSION_KEY`.
+/// Version 1 is the original layout, which predates the key.
+pub const STATE_VERSION: u16 = 2;
+
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Contract state layout of version 1, built on the legacy collections
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FusionDemoV1 {
+    pub orders: LegacyUnorderedMap,
+}
+
+/// Storage layout of the legacy `near_sdk::collections::UnorderedMap` of version 1:
+/// keys and values in two vectors indexed by a u64, and the index of each key under
+/// `key_index_prefix` followed by the key
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyUnorderedMap {
+    key_index_prefix: Vec<u8>,
+    keys: LegacyVector,
+    values: LegacyVector,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+struct LegacyVector {
+    len: u64,
+    prefix: Vec<u8>,
+}
+
+impl LegacyUnorderedMap {
+    /// Remove and return every order
+    fn drain(&mut self) -> Vec<(String, Order)> {
+        let mut orders = Vec::new();
+        while let (Some(key), Some(value)) = (self.keys.pop(), self.values.pop()) {
+            env::storage_remove(&[self.key_index_prefix.as_slice(), &key].concat());
+            orders.push((
+                String::try_from_slice(&key).expect("Invalid legacy key"),
+                Order::try_from_slice(&value).expect("Invalid legacy order"),
+            ));
+        }
+        orders
+    }
+}
+
+impl LegacyVector {
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        self.len = self.len.checked_sub(1)?;
+        let key = [self.prefix.as_slice(), &self.len.to_le_bytes()].concat();
+        let value = env::storage_read(&key).expect("Missing legacy entry");
+        env::storage_remove(&key);
+        Some(value)
+    }
+}
+
+#[near]
+impl FusionDemo {
+    /// Convert the stored state to the current layout after a code upgrade
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let version = Self::state_version();
+        let contract = match version {
+            1 => Self::migrate_from_v1(),
+            STATE_VERSION => env::panic_str("State already migrated"),
+            version => env::panic_str(&format!("Unknown state version {}", version)),
+        };
+
+        Self::write_state_version();
+        log!("Migrated state v{} to v{}", version, STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> u16 {
+        Self::state_version()
+    }
+}
+
+impl FusionDemo {
+    fn state_version() -> u16 {
+        env::storage_read(STATE_VERSION_KEY).map_or(1, |bytes| {
+            u16::from_le_bytes(bytes.try_into().expect("Invalid state version"))
+        })
+    }
+
+    pub(crate) fn write_state_version() {
+        env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.to_le_bytes());
+    }
+
+    /// Move the orders into an iterable map under the same prefix. The legacy map's
+    /// value vector lives where the new key vector goes, so it is cleared first.
+    /// The contract account becomes the owner, with no guardian and nothing paused.
+    fn migrate_from_v1() -> Self {
+        let mut old: FusionDemoV1 = env::state_read().expect("No v1 state to migrate");
+        let orders = old.orders.drain();
+
+        let mut contract = Self::init(IterableMap::new(StorageKey::Orders));
+        contract.orders.extend(orders);
+        contract
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::collections::UnorderedMap;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    /// Version 1 state as the original contract wrote it, with the legacy collections
+    #[derive(BorshDeserialize, BorshSerialize)]
+    struct OriginalState {
+        orders: UnorderedMap<String, Order>,
+    }
+
+    fn order(amount: u128) -> Order {
+        Order { maker: accounts(1), amount: U128(amount), hashlock: "lock".to_string(), completed: false }
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).build());
+        let mut state = OriginalState { orders: UnorderedMap::new(b"o") };
+        state.orders.insert(&"0x01".to_string(), &order(1));
+        state.orders.insert(&"0x02".to_string(), &order(2));
+        env::state_write(&state);
+
+        let contract = FusionDemo::migrate();
+        assert_eq!(FusionDemo::state_version(), STATE_VERSION);
+        assert_eq!(contract.orders.len(), 2);
+        assert_eq!(contract.owner, accounts(0));
+        assert!(contract.get_paused_methods().is_empty());
+        assert_eq!(contract.get_order("0x01".to_string()).unwrap().amount, U128(1));
+        assert_eq!(contract.get_order("0x02".to_string()).unwrap().amount, U128(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "State already migrated")]
+    fn test_migrate_twice_rejected() {
+        testing_env!(VMContextBuilder::new().build());
+        let _ = FusionDemo::new();
+        FusionDemo::migrate();
+    }
+}
@@ -0,0 +1,50 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Hash function used for hashlocks. Sha256 is the demo's original scheme and
+/// matches BTC/Lightning-style HTLCs; keccak256 matches EVM chains.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "lowercase")]
+#[borsh(use_discriminant = true)]
+pub enum HashAlgorithm {
+    Keccak256 = 0,
+    #[default]
+    Sha256 = 1,
+}
+
+impl HashAlgorithm {
+    /// Single hashing helper used by every hashlock verification path
+    pub fn hash(&self, data: &[u8]) -> [u8; 32] {
+        match self {
+            HashAlgorithm::Keccak256 => env::keccak256_array(data),
+            HashAlgorithm::Sha256 => env::sha256_array(data),
+        }
+    }
+
+    /// Check that `secret` unlocks a hex-encoded hashlock (any casing, optional 0x)
+    pub fn verify(&self, secret: &[u8], hashlock: &str) -> bool {
+        let hashlock = hashlock.strip_prefix("0x").unwrap_or(hashlock);
+        hex::encode(self.hash(secret)).eq_ignore_ascii_case(hashlock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_hash_vectors() {
+        testing_env!(VMContextBuilder::new().build());
+        let sha = "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b";
+        let keccak = "0x65462b0520ef7d3df61b9992ed3bea0c56ead753be7c8b3614e0ce01e4cac41b";
+
+        assert!(HashAlgorithm::Sha256.verify(b"secret", sha));
+        assert!(HashAlgorithm::Sha256.verify(b"secret", &sha.to_uppercase()));
+        assert!(HashAlgorithm::Keccak256.verify(b"secret", keccak));
+        assert!(!HashAlgorithm::Keccak256.verify(b"secret", sha));
+        assert!(!HashAlgorithm::Sha256.verify(b"other", sha));
+    }
+}
@@ -0,0 +1,310 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::store::LookupMap;
+use near_sdk::{
+    env, near, AccountId, BorshStorageKey, NearToken, Promise, 
+    require, log, PanicOnDefault
+};
+
+mod hashlock;
+mod migration;
+
+pub use hashlock::HashAlgorithm;
+pub use migration::{DemoOrderV1, SimpleFusionDemoV1, STATE_VERSION};
+
+/// Storage prefixes. `LegacyOrders` is the prefix of the original orders map.
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum StorageKey {
+    LegacyOrders = b'o',
+    Orders = b'h',
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DemoOrder {
+    pub maker: AccountId,
+    pub amount: NearToken,
+    pub hashlock: String,
+    pub deadline: u64,
+    pub completed: bool,
+    pub hash_algorithm: HashAlgorithm,
+}
+
+/// Methods the guardian can pause. Cancellation is never paused so makers can
+/// always get their NEAR back.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PausableMethod {
+    CreateOrder,
+    Claim,
+}
+
+const PAUSABLE_METHODS: [PausableMethod; 2] = [PausableMethod::CreateOrder, PausableMethod::Claim];
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct SimpleFusionDemo {
+    pub orders: LookupMap<String, DemoOrder>,
+    pub legacy_orders: LookupMap<String, DemoOrderV1>, // Untagged sha256 orders, moved to `orders` when touched
+    pub owner: AccountId,
+    pub guardian: Option<AccountId>,
+    pub paused_methods: Vec<PausableMethod>,
+}
+
+#[near]
+impl SimpleFusionDemo {
+    #[init]
+    pub fn new() -> Self {
+        Self::write_state_version();
+        Self {
+            orders: LookupMap::new(StorageKey::Orders),
+            legacy_orders: LookupMap::new(StorageKey::LegacyOrders),
+            owner: env::current_account_id(),
+            guardian: None,
+            paused_methods: Vec::new(),
+        }
+    }
+    
+    /// Create a new escrow order. The hashlock is the hex hash of the secret,
+    /// sha256 unless another algorithm is given.
+    #[payable]
+    pub fn create_order(
+        &mut self, 
+        order_hash: String, 
+        hashlock: String,
+        deadline_minutes: u64,
+        hash_algorithm: Option<HashAlgorithm>,
+    ) {
+        self.assert_not_paused(PausableMethod::CreateOrder);
+        let amount = env::attached_deposit();
+        require!(amount > NearToken::from_near(0), "Must attach NEAR tokens");
+        require!(
+            !self.orders.contains_key(&order_hash) && !self.legacy_orders.contains_key(&order_hash),
+            "Order already exists"
+        );
+        
+        let deadline = env::block_timestamp() + (deadline_minutes * 60 * 1_000_000_000);
+        
+        let order = DemoOrder {
+            maker: env::predecessor_account_id(),
+            amount,
+            hashlock,
+            deadline,
+            completed: false,
+            hash_algorithm: hash_algorithm.unwrap_or_default(),
+        };
+        
+        self.orders.insert(order_hash, order);
+        
+        log!("✅ Order created: {} NEAR locked by {}", 
+             amount.as_near(), 
+             env::predecessor_account_id());
+    }
+    
+    /// Claim NEAR tokens by revealing the secret
+    pub fn claim_with_secret(
+        &mut self, 
+        order_hash: String, 
+        secret: String
+    ) -> Promise {
+        self.assert_not_paused(PausableMethod::Claim);
+        let order = self.order_mut(&order_hash);
+        
+        require!(!order.completed, "Order already completed");
+        require!(env::block_timestamp() < order.deadline, "Order expired");
+        require!(order.hash_algorithm.verify(secret.as_bytes(), &order.hashlock), "Invalid secret");
+        
+        order.completed = true;
+        
+        log!("🎉 Secret revealed! Transferring {} NEAR to {}", 
+             order.amount.as_near(), 
+             env::predecessor_account_id());
+        
+        // Transfer NEAR tokens to the caller
+        Promise::new(env::predecessor_account_id())
+            .transfer(order.amount)
+    }
+    
+    /// Cancel order and refund (only by maker, after deadline)
+    pub fn cancel_order(&mut self, order_hash: String) -> Promise {
+        let order = self.order_mut(&order_hash);
+        
+        require!(order.maker == env::predecessor_account_id(), "Only maker can cancel");
+        require!(env::block_timestamp() >= order.deadline, "Cannot cancel before deadline");
+        require!(!order.completed, "Order already completed");
+        
+        order.completed = true;
+        
+        log!("❌ Order cancelled, refunding {} NEAR to {}", 
+             order.amount.as_near(), 
+             order.maker);
+        
+        Promise::new(order.maker.clone()).transfer(order.amount)
+    }
+    
+    /// View order details
+    pub fn get_order(&self, order_hash: String) -> Option<DemoOrder> {
+        self.orders.get(&order_hash).cloned()
+            .or_else(|| self.legacy_orders.get(&order_hash).cloned().map(DemoOrder::from))
+    }
+    
+    /// Get contract balance
+    pub fn get_balance(&self) -> NearToken {
+        env::account_balance()
+    }
+    
+    /// Set the account allowed to pause (only by owner)
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        require!(env::predecessor_account_id() == self.owner, "Only owner can set guardian");
+        self.guardian = guardian;
+    }
+    
+    /// Pause the given methods, or all of them if omitted (owner or guardian)
+    pub fn pause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_owner_or_guardian();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if !self.paused_methods.contains(&method) {
+                self.paused_methods.push(method);
+                Self::emit_pause_event("paused", method);
+            }
+        }
+    }
+    
+    /// Resume the given methods, or all of them if omitted (owner or guardian)
+    pub fn unpause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_owner_or_guardian();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if self.paused_methods.contains(&method) {
+                self.paused_methods.retain(|paused| *paused != method);
+                Self::emit_pause_event("unpaused", method);
+            }
+        }
+    }
+    
+    /// View paused methods
+    pub fn get_paused_methods(&self) -> Vec<PausableMethod> {
+        self.paused_methods.clone()
+    }
+}
+
+impl SimpleFusionDemo {
+    /// Order for a state change, moving a legacy order into `orders` first
+    fn order_mut(&mut self, order_hash: &String) -> &mut DemoOrder {
+        if let Some(order) = self.legacy_orders.remove(order_hash) {
+            self.orders.insert(order_hash.clone(), order.into());
+        }
+        self.orders.get_mut(order_hash).expect("Order not found")
+    }
+    
+    fn assert_owner_or_guardian(&self) {
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == self.owner || self.guardian.as_ref() == Some(&caller),
+            "Only owner or guardian can pause"
+        );
+    }
+    
+    fn assert_not_paused(&self, method: PausableMethod) {
+        require!(!self.paused_methods.contains(&method), "Method paused");
+    }
+    
+    fn emit_pause_event(event: &str, method: PausableMethod) {
+        let event = json!({
+            "standard": "simple-fusion-demo",
+            "version": "1.0.0",
+            "event": event,
+            "data": { "method": method, "by": env::predecessor_account_id() },
+        });
+        log!("EVENT_JSON:{}", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const MINUTE: u64 = 60 * 1_000_000_000;
+
+    fn call(predecessor: AccountId, deposit: NearToken, timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(predecessor)
+            .attached_deposit(deposit)
+            .block_timestamp(timestamp)
+            .build());
+    }
+
+    fn contract_with_order() -> SimpleFusionDemo {
+        call(accounts(0), NearToken::from_yoctonear(0), 0);
+        let mut contract = SimpleFusionDemo::new();
+        call(accounts(1), NearToken::from_near(1), 0);
+        contract.create_order("0x01".to_string(), hex::encode(env::sha256(b"secret")), 10, None);
+        contract
+    }
+
+    #[test]
+    fn test_claim_with_secret() {
+        let mut contract = contract_with_order();
+        call(accounts(2), NearToken::from_yoctonear(0), MINUTE);
+        let _ = contract.claim_with_secret("0x01".to_string(), "secret".to_string());
+        assert!(contract.get_order("0x01".to_string()).unwrap().completed);
+    }
+
+    #[test]
+    fn test_claim_keccak_order() {
+        call(accounts(0), NearToken::from_yoctonear(0), 0);
+        let mut contract = SimpleFusionDemo::new();
+        call(accounts(1), NearToken::from_near(1), 0);
+        let hashlock = hex::encode(env::keccak256(b"secret"));
+        contract.create_order("0x02".to_string(), hashlock, 10, Some(HashAlgorithm::Keccak256));
+
+        call(accounts(2), NearToken::from_yoctonear(0), MINUTE);
+        let _ = contract.claim_with_secret("0x02".to_string(), "secret".to_string());
+        assert!(contract.get_order("0x02".to_string()).unwrap().completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid secret")]
+    fn test_claim_rejects_wrong_secret() {
+        let mut contract = contract_with_order();
+        call(accounts(2), NearToken::from_yoctonear(0), MINUTE);
+        let _ = contract.claim_with_secret("0x01".to_string(), "other".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Method paused")]
+    fn test_guardian_pauses_claims() {
+        let mut contract = contract_with_order();
+        call(accounts(0), NearToken::from_yoctonear(0), 0);
+        contract.set_guardian(Some(accounts(3)));
+        call(accounts(3), NearToken::from_yoctonear(0), 0);
+        contract.pause(Some(vec![PausableMethod::Claim]));
+
+        call(accounts(2), NearToken::from_yoctonear(0), MINUTE);
+        let _ = contract.claim_with_secret("0x01".to_string(), "secret".to_string());
+    }
+
+    #[test]
+    fn test_cancel_while_paused() {
+        let mut contract = contract_with_order();
+        call(accounts(0), NearToken::from_yoctonear(0), 0);
+        contract.pause(None);
+
+        call(accounts(1), NearToken::from_yoctonear(0), 10 * MINUTE);
+        let _ = contract.cancel_order("0x01".to_string());
+        assert!(contract.get_order("0x01".to_string()).unwrap().completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner or guardian can pause")]
+    fn test_pause_requires_owner_or_guardian() {
+        let mut contract = contract_with_order();
+        call(accounts(1), NearToken::from_yoctonear(0), 0);
+        contract.pause(None);
+    }
+}
@@ -0,0 +1,156 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::store::LookupMap;
+use near_sdk::{env, log, near, AccountId, NearToken};
+
+use crate::{DemoOrder, HashAlgorithm, SimpleFusionDemo, SimpleFusionDemoExt, StorageKey};
+
+/// Layout version of the contract state, stored under `STATE_VERSION_KEY`.
+/// Version 1 is the original layout, which predates the key.
+pub const STATE_VERSION: u16 = 2;
+
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Order layout of state version 1, always hashed with sha256
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct DemoOrderV1 {
+    pub maker: AccountId,
+    pub amount: NearToken,
+    pub hashlock: String,
+    pub deadline: u64,
+    pub completed: bool,
+}
+
+/// Contract state layout of version 1, before the pause and hash algorithm tag. Its
+/// legacy `near_sdk::collections::LookupMap` has the same layout as the `store` one.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SimpleFusionDemoV1 {
+    pub orders: LookupMap<String, DemoOrderV1>,
+    pub owner: AccountId,
+}
+
+impl From<DemoOrderV1> for DemoOrder {
+    fn from(order: DemoOrderV1) -> Self {
+        Self {
+            maker: order.maker,
+            amount: order.amount,
+            hashlock: order.hashlock,
+            deadline: order.deadline,
+            completed: order.completed,
+            hash_algorithm: HashAlgorithm::Sha256,
+        }
+    }
+}
+
+#[near]
+impl SimpleFusionDemo {
+    /// Convert the stored state to the current layout after a code upgrade
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let version = Self::state_version();
+        let contract = match version {
+            1 => Self::migrate_from_v1(),
+            STATE_VERSION => env::panic_str("State already migrated"),
+            version => env::panic_str(&format!("Unknown state version {}", version)),
+        };
+
+        Self::write_state_version();
+        log!("Migrated state v{} to v{}", version, STATE_VERSION);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> u16 {
+        Self::state_version()
+    }
+}
+
+impl SimpleFusionDemo {
+    fn state_version() -> u16 {
+        env::storage_read(STATE_VERSION_KEY).map_or(1, |bytes| {
+            u16::from_le_bytes(bytes.try_into().expect("Invalid state version"))
+        })
+    }
+
+    pub(crate) fn write_state_version() {
+        env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.to_le_bytes());
+    }
+
+    /// The v1 orders map cannot be iterated, so its entries stay in place as legacy
+    /// orders and move to the tagged map when they are claimed or cancelled
+    fn migrate_from_v1() -> Self {
+        let old: SimpleFusionDemoV1 = env::state_read().expect("No v1 state to migrate");
+        Self {
+            orders: LookupMap::new(StorageKey::Orders),
+            legacy_orders: LookupMap::new(StorageKey::LegacyOrders),
+            owner: old.owner,
+            guardian: None,
+            paused_methods: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::collections;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    /// Version 1 state as the original contract wrote it, with the legacy collections
+    #[derive(BorshDeserialize, BorshSerialize)]
+    struct OriginalState {
+        orders: collections::LookupMap<String, DemoOrderV1>,
+        owner: AccountId,
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut state = OriginalState { orders: collections::LookupMap::new(b"o"), owner: accounts(0) };
+        let order = DemoOrderV1 {
+            maker: accounts(1),
+            amount: NearToken::from_near(1),
+            hashlock: hex::encode(env::sha256(b"secret")),
+            deadline: 10,
+            completed: false,
+        };
+        state.orders.insert(&"0x01".to_string(), &order);
+        env::state_write(&state);
+
+        let contract = SimpleFusionDemo::migrate();
+        assert_eq!(SimpleFusionDemo::state_version(), STATE_VERSION);
+        assert_eq!(contract.owner, accounts(0));
+        assert!(contract.get_paused_methods().is_empty());
+        let order = contract.get_order("0x01".to_string()).unwrap();
+        assert_eq!(order.amount, NearToken::from_near(1));
+        assert_eq!(order.hash_algorithm, HashAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn test_claim_legacy_order() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut state = OriginalState { orders: collections::LookupMap::new(b"o"), owner: accounts(0) };
+        let order = DemoOrderV1 {
+            maker: accounts(1),
+            amount: NearToken::from_near(1),
+            hashlock: hex::encode(env::sha256(b"secret")),
+            deadline: 10,
+            completed: false,
+        };
+        state.orders.insert(&"0x01".to_string(), &order);
+        env::state_write(&state);
+        let mut contract = SimpleFusionDemo::migrate();
+
+        let _ = contract.claim_with_secret("0x01".to_string(), "secret".to_string());
+        assert!(!contract.legacy_orders.contains_key("0x01"));
+        assert!(contract.get_order("0x01".to_string()).unwrap().completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "State already migrated")]
+    fn test_migrate_twice_rejected() {
+        testing_env!(VMContextBuilder::new().build());
+        let _ = SimpleFusionDemo::new();
+        SimpleFusionDemo::migrate();
+    }
+}
@@ -1,7 +1,11 @@
+// Escrow initializers take the order terms individually, also in the generated `ext` interface
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, Promise, NearToken, Gas};
+use near_sdk::serde_json::json;
 use near_contract_standards::fungible_token::Balance;
-use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 // Timelock stages corresponding to Ethereum implementation
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug)]
@@ -17,12 +21,225 @@ pub enum Stage {
     DstCancellation,        // Cancellation on Near
 }
 
+/// Decode a hex string, with or without 0x prefix
+pub fn decode_hex(value: &str) -> Result<Vec<u8>, String> {
+    let stripped = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(stripped).map_err(|e| format!("Invalid hex: {}", e))
+}
+
+// 32-byte value (secret or hashlock), matching Solidity `bytes32`.
+// JSON accepts a hex string (optionally 0x-prefixed) or a 32-element byte array,
+// and always serializes as 0x-prefixed hex.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bytes32(pub [u8; 32]);
+
+impl Bytes32 {
+    /// Parse a 64-character hex string, with or without 0x prefix
+    pub fn from_hex(value: &str) -> Result<Self, String> {
+        let stripped = value.strip_prefix("0x").unwrap_or(value);
+        if stripped.len() != 64 {
+            return Err(format!("Expected 32 bytes of hex, got {} characters", stripped.len()));
+        }
+        Self::from_slice(&decode_hex(stripped)?)
+    }
+
+    /// Convert a byte slice, rejecting anything that is not exactly 32 bytes
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        let array: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| format!("Expected 32 bytes, got {}", bytes.len()))?;
+        Ok(Self(array))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0u8; 32]
+    }
+}
+
+impl From<[u8; 32]> for Bytes32 {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Serialize for Bytes32 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(self.0)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes32 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(crate = "near_sdk::serde", untagged)]
+        enum Repr {
+            Hex(String),
+            Bytes(Vec<u8>),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Hex(value) => Bytes32::from_hex(&value),
+            Repr::Bytes(bytes) => Bytes32::from_slice(&bytes),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+// Hash function used for hashlocks (keccak256 for EVM chains, sha256 for BTC/Lightning HTLCs)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "lowercase")]
+#[borsh(use_discriminant = true)]
+pub enum HashAlgorithm {
+    #[default]
+    Keccak256 = 0,
+    Sha256 = 1,
+}
+
+impl HashAlgorithm {
+    /// Single hashing helper used by every hashlock verification path
+    pub fn hash(&self, data: &[u8]) -> [u8; 32] {
+        match self {
+            HashAlgorithm::Keccak256 => env::keccak256_array(data),
+            HashAlgorithm::Sha256 => env::sha256_array(data),
+        }
+    }
+}
+
+// Domain separator for maker-signed order intents
+pub const ORDER_INTENT_DOMAIN: &str = "fusion-near-escrow:order:v1";
+
+// Maker signature over the canonical order intent, supplied at escrow creation
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde", tag = "scheme", rename_all = "lowercase")]
+pub enum MakerSignature {
+    Ed25519 {
+        public_key: Bytes32,     // Maker's NEAR access key
+        signature: String,       // 64-byte signature over the intent digest, hex
+    },
+    Secp256k1 {
+        eth_address: String,     // Maker's Ethereum address, hex
+        signature: String,       // 65-byte r || s || v personal_sign signature, hex
+    },
+}
+
+// Signer recorded in immutables after successful verification
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", tag = "scheme", rename_all = "lowercase")]
+pub enum MakerSigner {
+    Ed25519 { public_key: Bytes32 },
+    Secp256k1 { eth_address: String },
+}
+
+impl MakerSignature {
+    /// Signer claimed by this signature, as recorded in immutables
+    pub fn signer(&self) -> MakerSigner {
+        match self {
+            MakerSignature::Ed25519 { public_key, .. } => MakerSigner::Ed25519 { public_key: *public_key },
+            MakerSignature::Secp256k1 { eth_address, .. } => MakerSigner::Secp256k1 {
+                eth_address: format!("0x{}", eth_address.strip_prefix("0x").unwrap_or(eth_address).to_lowercase()),
+            },
+        }
+    }
+
+    /// Verify signature over the intent digest, panicking on mismatch. The signing key
+    /// must be derivable from `maker`, so a resolver cannot sign with its own key:
+    /// ed25519 requires an implicit account (hex of the key), secp256k1 an
+    /// eth-implicit `0x` account (the Ethereum address). A contract cannot read another
+    /// account's access keys, so makers with named accounts (`alice.near`) cannot sign
+    /// orders; they have to trade from their implicit or eth-implicit account.
+    pub fn verify(&self, maker: &AccountId, digest: &[u8; 32]) {
+        match self {
+            MakerSignature::Ed25519 { public_key, signature } => {
+                assert_eq!(maker.as_str(), hex::encode(public_key.0), "Key does not belong to maker");
+
+                let signature: [u8; 64] = decode_hex(signature)
+                    .and_then(|bytes| bytes.try_into().map_err(|_| "Expected 64-byte signature".to_string()))
+                    .unwrap_or_else(|e| env::panic_str(&e));
+                assert!(env::ed25519_verify(&signature, digest, &public_key.0), "Invalid maker signature");
+            }
+            MakerSignature::Secp256k1 { eth_address, signature } => {
+                let expected_address = decode_hex(eth_address).unwrap_or_else(|e| env::panic_str(&e));
+                assert_eq!(expected_address.len(), 20, "Expected 20-byte Ethereum address");
+                assert_eq!(
+                    maker.as_str(),
+                    format!("0x{}", hex::encode(&expected_address)),
+                    "Key does not belong to maker"
+                );
+
+                let signature = decode_hex(signature).unwrap_or_else(|e| env::panic_str(&e));
+                assert_eq!(signature.len(), 65, "Expected 65-byte signature");
+                let v = match signature[64] {
+                    27 | 28 => signature[64] - 27,
+                    0 | 1 => signature[64],
+                    _ => env::panic_str("Invalid signature recovery id"),
+                };
+
+                // EIP-191 personal_sign over the 32-byte digest
+                let mut message = b"\x19Ethereum Signed Message:\n32".to_vec();
+                message.extend_from_slice(digest);
+                let hash = env::keccak256_array(&message);
+
+                let public_key = env::ecrecover(&hash, &signature[..64], v, true)
+                    .unwrap_or_else(|| env::panic_str("Invalid maker signature"));
+                let recovered_address = &env::keccak256_array(public_key)[12..];
+                assert_eq!(recovered_address, &expected_address[..], "Invalid maker signature");
+            }
+        }
+    }
+}
+
+// Canonical order encoding signed by the maker: keccak256(borsh(intent)).
+// The escrow account id binds each signature to one escrow. An account can be deleted
+// and created again, so the escrow must also be initialized by the signed router, which
+// accepts each `(maker, nonce)` pair once per escrow. Taker and safety deposit are
+// chosen by the resolver and are not signed.
+#[derive(BorshSerialize, Clone, Debug)]
+pub struct OrderIntent {
+    pub domain: String,
+    pub escrow_id: AccountId,
+    pub router: AccountId,
+    pub hashlock: [u8; 32],
+    pub hash_algorithm: HashAlgorithm,
+    pub token_id: AccountId,
+    pub amount: Balance,
+    pub maker: AccountId,
+    pub timelocks: u64,
+    pub total_parts: u64,               // 0 for single fill
+    pub fee_config: Option<FeeConfig>,
+    pub nonce: u64,
+    pub guardian: Option<AccountId>,
+}
+
+impl OrderIntent {
+    pub fn from_immutables(escrow_id: AccountId, immutables: &EscrowImmutables) -> Self {
+        Self {
+            domain: ORDER_INTENT_DOMAIN.to_string(),
+            escrow_id,
+            router: immutables.router.clone(),
+            hashlock: immutables.hashlock,
+            hash_algorithm: immutables.hash_algorithm,
+            token_id: immutables.token_id.clone(),
+            amount: immutables.amount,
+            maker: immutables.maker.clone(),
+            timelocks: immutables.timelocks,
+            total_parts: immutables.partial_fill_info.as_ref().map_or(0, |info| info.total_parts),
+            fee_config: immutables.fee_config.clone(),
+            nonce: immutables.nonce,
+            guardian: immutables.guardian.clone(),
+        }
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        env::keccak256_array(borsh::to_vec(self).expect("Failed to serialize order intent"))
+    }
+}
+
 // Merkle proof structure for partial fills
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct MerkleProof {
     pub index: u64,              // Fill percentage index (0-N)
-    pub secret_hash: [u8; 32],   // keccak256(secret) for this index
+    pub secret_hash: [u8; 32],   // hash(secret) for this index
     pub proof: Vec<[u8; 32]>,    // Merkle proof path
 }
 
@@ -36,11 +253,92 @@ pub struct PartialFillInfo {
     pub last_validated: u64,         // Last validated index for sequential fills
 }
 
+// Basis-point denominator for fee calculations
+pub const MAX_BPS: u16 = 10_000;
+
+// Fee configuration for protocol and integrator fees
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    pub protocol_fee_bps: u16,                      // Protocol fee in basis points
+    pub protocol_fee_recipient: AccountId,          // Protocol treasury account
+    pub integrator_fee_bps: u16,                    // Integrator fee in basis points (0 if none)
+    pub integrator_fee_recipient: Option<AccountId>, // Integrator account, required if fee > 0
+}
+
+// Result of splitting a withdrawal amount between maker and fee recipients
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeSplit {
+    pub maker_amount: Balance,
+    pub protocol_fee: Balance,
+    pub integrator_fee: Balance,
+}
+
+impl FeeConfig {
+    /// Validate fee configuration bounds
+    fn assert_valid(&self) {
+        assert!(
+            self.protocol_fee_bps as u32 + self.integrator_fee_bps as u32 <= MAX_BPS as u32,
+            "Total fee exceeds 100%"
+        );
+        if self.integrator_fee_bps > 0 {
+            assert!(self.integrator_fee_recipient.is_some(), "Integrator fee requires a recipient");
+        }
+    }
+
+    /// Split amount into maker payout and fees.
+    /// Fees are rounded down, so any remainder always goes to the maker.
+    pub fn split(&self, amount: Balance) -> FeeSplit {
+        let protocol_fee = Self::fee(amount, self.protocol_fee_bps);
+        let integrator_fee = Self::fee(amount, self.integrator_fee_bps);
+        FeeSplit {
+            maker_amount: amount - protocol_fee - integrator_fee,
+            protocol_fee,
+            integrator_fee,
+        }
+    }
+
+    /// `amount * bps / MAX_BPS` rounded down, divided first so it cannot overflow
+    fn fee(amount: Balance, bps: u16) -> Balance {
+        let (whole, remainder) = (amount / MAX_BPS as u128, amount % MAX_BPS as u128);
+        whole * bps as u128 + remainder * bps as u128 / MAX_BPS as u128
+    }
+}
+
+// Entry points the guardian can pause. Cancellation and rescue are never
+// pausable, so locked funds can always be recovered. The guardian is chosen per
+// escrow by the maker and can only pause that escrow; new escrows are stopped by
+// the protocol guardian, which pauses `init_escrow` on the router.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PausableMethod {
+    Withdraw,       // withdraw and withdraw_partial
+    PublicWithdraw,
+}
+
+pub const PAUSABLE_METHODS: [PausableMethod; 2] = [PausableMethod::Withdraw, PausableMethod::PublicWithdraw];
+
+// Escrow status view including fees collected so far
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowStatus {
+    pub withdrawn: bool,
+    pub cancelled: bool,
+    pub filled_amount: Balance,
+    pub remaining_amount: Balance,
+    pub fee_config: Option<FeeConfig>,
+    pub protocol_fees_paid: Balance,
+    pub integrator_fees_paid: Balance,
+    pub paused_methods: Vec<PausableMethod>,
+}
+
 // Escrow immutable data structure
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct EscrowImmutables {
-    pub hashlock: [u8; 32],           // keccak256(secret) or Merkle root for partial fills
+    pub hashlock: [u8; 32],           // hash(secret) or Merkle root for partial fills
+    pub hash_algorithm: HashAlgorithm, // Hash function for hashlock and Merkle tree
     pub token_id: AccountId,          // FT contract account
     pub amount: Balance,              // Token amount
     pub maker: AccountId,             // Near user account
@@ -49,6 +347,11 @@ pub struct EscrowImmutables {
     pub timelocks: u64,               // Packed timelock stages
     pub deployed_at: u64,             // Block height
     pub partial_fill_info: Option<PartialFillInfo>, // None for single fill, Some for partial fills
+    pub fee_config: Option<FeeConfig>,  // None if no fees are taken
+    pub nonce: u64,                   // Maker-chosen nonce included in the signed intent
+    pub maker_signer: MakerSigner,    // Key that signed the order intent
+    pub guardian: Option<AccountId>,  // Account allowed to pause withdrawals, signed by the maker
+    pub router: AccountId,            // Account that initialized the escrow, signed by the maker
 }
 
 // Main escrow contract for destination chain (Near)
@@ -58,9 +361,12 @@ pub struct EscrowDst {
     pub immutables: EscrowImmutables,
     pub withdrawn: bool,
     pub cancelled: bool,
-    pub revealed_secret: Option<Vec<u8>>,
+    pub revealed_secret: Option<Bytes32>,
     pub partial_fill_state: Option<PartialFillInfo>, // Mutable state for partial fills
     pub filled_amount: Balance,                      // Amount already filled in partial fills
+    pub protocol_fees_paid: Balance,                 // Protocol fees paid out so far
+    pub integrator_fees_paid: Balance,               // Integrator fees paid out so far
+    pub paused_methods: Vec<PausableMethod>,         // Methods currently paused by the guardian
 }
 
 // Merkle tree validation utilities
@@ -68,11 +374,11 @@ impl EscrowDst {
     /// Validate Merkle proof for partial fill
     fn validate_merkle_proof(&self, proof: &MerkleProof) -> bool {
         if let Some(ref partial_info) = self.partial_fill_state {
-            // Compute leaf hash: keccak256(index || secret_hash)
+            // Compute leaf hash: hash(index || secret_hash)
             let mut leaf_data = Vec::new();
             leaf_data.extend_from_slice(&proof.index.to_le_bytes());
             leaf_data.extend_from_slice(&proof.secret_hash);
-            let leaf_hash = env::keccak256(&leaf_data);
+            let leaf_hash = self.immutables.hash_algorithm.hash(&leaf_data);
 
             // Verify Merkle proof
             self.verify_merkle_proof(leaf_hash, &proof.proof, partial_info.merkle_root)
@@ -82,8 +388,8 @@ impl EscrowDst {
     }
 
     /// Verify Merkle proof against root
-    fn verify_merkle_proof(&self, leaf: Vec<u8>, proof: &[[u8; 32]], root: [u8; 32]) -> bool {
-        let mut computed_hash: [u8; 32] = leaf.try_into().unwrap_or_default();
+    fn verify_merkle_proof(&self, leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+        let mut computed_hash = leaf;
         
         for proof_element in proof {
             // Determine hash order (smaller hash first for consistent ordering)
@@ -91,14 +397,12 @@ impl EscrowDst {
                 let mut combined = Vec::new();
                 combined.extend_from_slice(&computed_hash);
                 combined.extend_from_slice(proof_element);
-                let hash_vec = env::keccak256(&combined);
-                computed_hash = hash_vec.try_into().unwrap_or_default();
+                computed_hash = self.immutables.hash_algorithm.hash(&combined);
             } else {
                 let mut combined = Vec::new();
                 combined.extend_from_slice(proof_element);
                 combined.extend_from_slice(&computed_hash);
-                let hash_vec = env::keccak256(&combined);
-                computed_hash = hash_vec.try_into().unwrap_or_default();
+                computed_hash = self.immutables.hash_algorithm.hash(&combined);
             }
         }
         
@@ -148,41 +452,66 @@ impl EscrowDst {
 
 #[near_bindgen]
 impl EscrowDst {
-    /// Initialize new escrow contract (single fill)
+    /// Initialize new escrow contract (single fill). Must be called by the router named
+    /// in the maker's signed intent, which rejects `(maker, nonce)` pairs this escrow
+    /// was already initialized with.
     #[init]
     pub fn new(
-        hashlock: [u8; 32],
+        hashlock: Bytes32,
         token_id: AccountId,
         amount: Balance,
         maker: AccountId,
         taker: AccountId,
         safety_deposit: Balance,
         timelocks: u64,
+        fee_config: Option<FeeConfig>,
+        hash_algorithm: Option<HashAlgorithm>,
+        nonce: u64,
+        guardian: Option<AccountId>,
+        maker_signature: MakerSignature,
     ) -> Self {
+        assert!(!hashlock.is_zero(), "Invalid hashlock");
+        if let Some(ref fees) = fee_config {
+            fees.assert_valid();
+        }
+
+        let immutables = EscrowImmutables {
+            hashlock: hashlock.0,
+            hash_algorithm: hash_algorithm.unwrap_or_default(),
+            token_id,
+            amount,
+            maker,
+            taker,
+            safety_deposit,
+            timelocks,
+            deployed_at: env::block_height(),
+            partial_fill_info: None,
+            fee_config,
+            nonce,
+            maker_signer: maker_signature.signer(),
+            guardian,
+            router: env::predecessor_account_id(),
+        };
+        Self::verify_order_intent(&immutables, &maker_signature);
+
         Self {
-            immutables: EscrowImmutables {
-                hashlock,
-                token_id,
-                amount,
-                maker,
-                taker,
-                safety_deposit,
-                timelocks,
-                deployed_at: env::block_height(),
-                partial_fill_info: None,
-            },
+            immutables,
             withdrawn: false,
             cancelled: false,
             revealed_secret: None,
             partial_fill_state: None,
             filled_amount: 0,
+            protocol_fees_paid: 0,
+            integrator_fees_paid: 0,
+            paused_methods: Vec::new(),
         }
     }
 
-    /// Initialize new escrow contract with partial fill support
+    /// Initialize new escrow contract with partial fill support. Must be called by the
+    /// router named in the maker's signed intent.
     #[init]
     pub fn new_with_partial_fills(
-        merkle_root: [u8; 32],
+        merkle_root: Bytes32,
         token_id: AccountId,
         amount: Balance,
         maker: AccountId,
@@ -190,56 +519,95 @@ impl EscrowDst {
         safety_deposit: Balance,
         timelocks: u64,
         total_parts: u64,
+        fee_config: Option<FeeConfig>,
+        hash_algorithm: Option<HashAlgorithm>,
+        nonce: u64,
+        guardian: Option<AccountId>,
+        maker_signature: MakerSignature,
     ) -> Self {
+        assert!(!merkle_root.is_zero(), "Invalid hashlock");
+        if let Some(ref fees) = fee_config {
+            fees.assert_valid();
+        }
+
         let partial_fill_info = PartialFillInfo {
-            merkle_root,
+            merkle_root: merkle_root.0,
             total_parts,
             used_indices: Vec::new(),
             last_validated: 0,
         };
 
+        let immutables = EscrowImmutables {
+            hashlock: merkle_root.0, // Use Merkle root as hashlock for partial fills
+            hash_algorithm: hash_algorithm.unwrap_or_default(),
+            token_id,
+            amount,
+            maker,
+            taker,
+            safety_deposit,
+            timelocks,
+            deployed_at: env::block_height(),
+            partial_fill_info: Some(partial_fill_info.clone()),
+            fee_config,
+            nonce,
+            maker_signer: maker_signature.signer(),
+            guardian,
+            router: env::predecessor_account_id(),
+        };
+        Self::verify_order_intent(&immutables, &maker_signature);
+
         Self {
-            immutables: EscrowImmutables {
-                hashlock: merkle_root, // Use Merkle root as hashlock for partial fills
-                token_id,
-                amount,
-                maker,
-                taker,
-                safety_deposit,
-                timelocks,
-                deployed_at: env::block_height(),
-                partial_fill_info: Some(partial_fill_info.clone()),
-            },
+            immutables,
             withdrawn: false,
             cancelled: false,
             revealed_secret: None,
             partial_fill_state: Some(partial_fill_info),
             filled_amount: 0,
+            protocol_fees_paid: 0,
+            integrator_fees_paid: 0,
+            paused_methods: Vec::new(),
         }
     }
 
     /// Withdraw tokens by revealing the secret (private phase) - single fill
-    pub fn withdraw(&mut self, secret: Vec<u8>) -> Promise {
-        self.validate_withdraw(&secret);
-        self.execute_withdrawal(secret, &env::predecessor_account_id())
+    pub fn withdraw(&mut self, secret: Bytes32) -> Promise {
+        let caller = env::predecessor_account_id();
+        self.validate_withdraw(&caller, &secret);
+        self.execute_withdrawal(secret, &caller)
+    }
+
+    /// `withdraw` relayed by the router's `batch_settle`, which passes its own caller as
+    /// `taker`. The safety deposit goes to the taker.
+    pub fn withdraw_via_router(&mut self, taker: AccountId, secret: Bytes32) -> Promise {
+        self.assert_router();
+        self.validate_withdraw(&taker, &secret);
+        self.execute_withdrawal(secret, &taker)
     }
 
     /// Withdraw tokens with partial fill using Merkle proof
-    pub fn withdraw_partial(&mut self, secret: Vec<u8>, proof: MerkleProof) -> Promise {
+    pub fn withdraw_partial(&mut self, secret: Bytes32, proof: MerkleProof) -> Promise {
         self.validate_partial_withdraw(&secret, &proof);
         self.execute_partial_withdrawal(secret, proof, &env::predecessor_account_id())
     }
 
     /// Public withdrawal allowing anyone to withdraw after timeout
-    pub fn public_withdraw(&mut self, secret: Vec<u8>) -> Promise {
+    pub fn public_withdraw(&mut self, secret: Bytes32) -> Promise {
         self.validate_public_withdraw(&secret);
         self.execute_withdrawal(secret, &env::predecessor_account_id())
     }
 
     /// Cancel the escrow (private phase)
     pub fn cancel(&mut self) -> Promise {
-        self.validate_cancel();
-        self.execute_cancellation(&env::predecessor_account_id())
+        let caller = env::predecessor_account_id();
+        self.validate_cancel(&caller);
+        self.execute_cancellation(&caller)
+    }
+
+    /// `cancel` relayed by the router's `batch_settle` on behalf of `taker`
+    pub fn cancel_via_router(&mut self, taker: AccountId) -> Promise {
+        self.assert_router();
+        self.validate_cancel(&taker);
+        self.execute_cancellation(&taker)
     }
 
     /// Rescue stuck funds after extended timeout
@@ -255,6 +623,29 @@ impl EscrowDst {
         )
     }
 
+    /// Pause withdrawals (all pausable methods if `methods` is omitted). Guardian only.
+    pub fn pause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_guardian();
+        for method in methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec()) {
+            if !self.paused_methods.contains(&method) {
+                self.paused_methods.push(method);
+                Self::emit_pause_event("paused", method);
+            }
+        }
+    }
+
+    /// Resume paused methods (all of them if `methods` is omitted). Guardian only.
+    pub fn unpause(&mut self, methods: Option<Vec<PausableMethod>>) {
+        self.assert_guardian();
+        let methods = methods.unwrap_or_else(|| PAUSABLE_METHODS.to_vec());
+        for method in methods {
+            if self.paused_methods.contains(&method) {
+                self.paused_methods.retain(|paused| *paused != method);
+                Self::emit_pause_event("unpaused", method);
+            }
+        }
+    }
+
     // View functions
     pub fn get_immutables(&self) -> &EscrowImmutables {
         &self.immutables
@@ -268,25 +659,77 @@ impl EscrowDst {
         self.cancelled
     }
 
-    pub fn get_revealed_secret(&self) -> Option<&Vec<u8>> {
-        self.revealed_secret.as_ref()
+    pub fn get_revealed_secret(&self) -> Option<Bytes32> {
+        self.revealed_secret
     }
 
     pub fn get_partial_fill_state(&self) -> Option<&PartialFillInfo> {
         self.partial_fill_state.as_ref()
     }
 
+    pub fn get_order_digest(&self) -> Bytes32 {
+        OrderIntent::from_immutables(env::current_account_id(), &self.immutables).digest().into()
+    }
+
+    pub fn get_status(&self) -> EscrowStatus {
+        EscrowStatus {
+            withdrawn: self.withdrawn,
+            cancelled: self.cancelled,
+            filled_amount: self.filled_amount,
+            remaining_amount: self.immutables.amount - self.filled_amount,
+            fee_config: self.immutables.fee_config.clone(),
+            protocol_fees_paid: self.protocol_fees_paid,
+            integrator_fees_paid: self.integrator_fees_paid,
+            paused_methods: self.paused_methods.clone(),
+        }
+    }
+
+    pub fn is_paused(&self, method: PausableMethod) -> bool {
+        self.paused_methods.contains(&method)
+    }
+
     // Private helper functions
-    fn validate_withdraw(&self, secret: &[u8]) {
+    fn assert_guardian(&self) {
+        assert!(
+            self.immutables.guardian.as_ref() == Some(&env::predecessor_account_id()),
+            "Only guardian can pause"
+        );
+    }
+
+    fn assert_router(&self) {
+        assert_eq!(env::predecessor_account_id(), self.immutables.router, "Only router can relay");
+    }
+
+    fn assert_not_paused(&self, method: PausableMethod) {
+        assert!(!self.is_paused(method), "Method paused");
+    }
+
+    // NEP-297 event for pause state changes
+    fn emit_pause_event(event: &str, method: PausableMethod) {
+        let event = json!({
+            "standard": "escrow-dst",
+            "version": "1.0.0",
+            "event": event,
+            "data": { "method": method, "by": env::predecessor_account_id() },
+        });
+        env::log_str(&format!("EVENT_JSON:{}", event));
+    }
+
+    fn verify_order_intent(immutables: &EscrowImmutables, maker_signature: &MakerSignature) {
+        let digest = OrderIntent::from_immutables(env::current_account_id(), immutables).digest();
+        maker_signature.verify(&immutables.maker, &digest);
+    }
+
+    fn validate_withdraw(&self, caller: &AccountId, secret: &Bytes32) {
+        self.assert_not_paused(PausableMethod::Withdraw);
         assert!(!self.withdrawn, "Already withdrawn");
         assert!(!self.cancelled, "Already cancelled");
-        assert_eq!(env::predecessor_account_id(), self.immutables.taker, "Only taker can withdraw");
+        assert_eq!(*caller, self.immutables.taker, "Only taker can withdraw");
         
         // For single fills, verify secret matches hashlock directly
         if self.immutables.partial_fill_info.is_none() {
-            let hash = env::keccak256(secret);
-            let hash_array: [u8; 32] = hash.try_into().unwrap_or_default();
-            assert_eq!(hash_array, self.immutables.hashlock, "Invalid secret");
+            let hash = self.immutables.hash_algorithm.hash(&secret.0);
+            assert_eq!(hash, self.immutables.hashlock, "Invalid secret");
         } else {
             env::panic_str("Use withdraw_partial for partial fills");
         }
@@ -296,7 +739,8 @@ impl EscrowDst {
         assert!(matches!(current_stage, Stage::DstWithdrawal), "Not in withdrawal stage");
     }
 
-    fn validate_partial_withdraw(&self, secret: &[u8], proof: &MerkleProof) {
+    fn validate_partial_withdraw(&self, secret: &Bytes32, proof: &MerkleProof) {
+        self.assert_not_paused(PausableMethod::Withdraw);
         assert!(!self.withdrawn, "Already withdrawn");
         assert!(!self.cancelled, "Already cancelled");
         assert_eq!(env::predecessor_account_id(), self.immutables.taker, "Only taker can withdraw");
@@ -305,9 +749,8 @@ impl EscrowDst {
         assert!(self.immutables.partial_fill_info.is_some(), "Not a partial fill escrow");
         
         // Verify secret matches the proof's secret hash
-        let secret_hash = env::keccak256(secret);
-        let secret_hash_array: [u8; 32] = secret_hash.try_into().unwrap_or_default();
-        assert_eq!(secret_hash_array, proof.secret_hash, "Secret doesn't match proof");
+        let secret_hash = self.immutables.hash_algorithm.hash(&secret.0);
+        assert_eq!(secret_hash, proof.secret_hash, "Secret doesn't match proof");
         
         // Validate Merkle proof
         assert!(self.validate_merkle_proof(proof), "Invalid Merkle proof");
@@ -321,24 +764,24 @@ impl EscrowDst {
         assert!(matches!(current_stage, Stage::DstWithdrawal), "Not in withdrawal stage");
     }
 
-    fn validate_public_withdraw(&self, secret: &[u8]) {
+    fn validate_public_withdraw(&self, secret: &Bytes32) {
+        self.assert_not_paused(PausableMethod::PublicWithdraw);
         assert!(!self.withdrawn, "Already withdrawn");
         assert!(!self.cancelled, "Already cancelled");
         
         // Verify secret matches hashlock
-        let hash = env::keccak256(secret);
-        let hash_array: [u8; 32] = hash.try_into().unwrap_or_default();
-        assert_eq!(hash_array, self.immutables.hashlock, "Invalid secret");
+        let hash = self.immutables.hash_algorithm.hash(&secret.0);
+        assert_eq!(hash, self.immutables.hashlock, "Invalid secret");
         
         // Check timelock stage
         let current_stage = self.get_current_stage();
         assert!(matches!(current_stage, Stage::DstPublicWithdrawal), "Not in public withdrawal stage");
     }
 
-    fn validate_cancel(&self) {
+    fn validate_cancel(&self, caller: &AccountId) {
         assert!(!self.withdrawn, "Already withdrawn");
         assert!(!self.cancelled, "Already cancelled");
-        assert_eq!(env::predecessor_account_id(), self.immutables.taker, "Only taker can cancel");
+        assert_eq!(*caller, self.immutables.taker, "Only taker can cancel");
         
         // Check timelock stage
         let current_stage = self.get_current_stage();
@@ -354,25 +797,20 @@ impl EscrowDst {
         assert!(blocks_passed > rescue_delay, "Rescue delay not met");
     }
 
-    fn execute_withdrawal(&mut self, secret: Vec<u8>, caller: &AccountId) -> Promise {
+    fn execute_withdrawal(&mut self, secret: Bytes32, caller: &AccountId) -> Promise {
         self.withdrawn = true;
         self.revealed_secret = Some(secret);
+        self.filled_amount = self.immutables.amount;
 
-        // Transfer tokens to maker and safety deposit to caller
-        let token_transfer = Promise::new(self.immutables.token_id.clone()).function_call(
-            "ft_transfer".to_string(),
-            format!(r#"{{"receiver_id": "{}", "amount": "{}"}}"#, 
-                   self.immutables.maker, self.immutables.amount).into_bytes(),
-            NearToken::from_yoctonear(1), // 1 yoctoNEAR for security
-            Gas::from_tgas(30),
-        );
+        // Transfer tokens to maker (minus fees) and safety deposit to caller
+        let token_transfer = self.transfer_with_fees(self.immutables.amount);
 
         let safety_deposit_transfer = Promise::new(caller.clone()).transfer(NearToken::from_yoctonear(self.immutables.safety_deposit));
 
         token_transfer.and(safety_deposit_transfer)
     }
 
-    fn execute_partial_withdrawal(&mut self, secret: Vec<u8>, proof: MerkleProof, caller: &AccountId) -> Promise {
+    fn execute_partial_withdrawal(&mut self, secret: Bytes32, proof: MerkleProof, caller: &AccountId) -> Promise {
         // Calculate fill amount for this index
         let fill_amount = self.calculate_partial_amount(proof.index) - self.filled_amount;
         
@@ -392,32 +830,57 @@ impl EscrowDst {
         }
 
         // Transfer partial amount to maker and proportional safety deposit to caller
-        let proportional_deposit = (self.immutables.safety_deposit * fill_amount as u128) / self.immutables.amount;
+        let proportional_deposit = (self.immutables.safety_deposit * fill_amount) / self.immutables.amount;
         
-        let token_transfer = Promise::new(self.immutables.token_id.clone()).function_call(
-            "ft_transfer".to_string(),
-            format!(r#"{{"receiver_id": "{}", "amount": "{}"}}"#, 
-                   self.immutables.maker, fill_amount).into_bytes(),
-            NearToken::from_yoctonear(1), // 1 yoctoNEAR for security
-            Gas::from_tgas(30),
-        );
+        let token_transfer = self.transfer_with_fees(fill_amount);
 
         let safety_deposit_transfer = Promise::new(caller.clone()).transfer(NearToken::from_yoctonear(proportional_deposit));
 
         token_transfer.and(safety_deposit_transfer)
     }
 
-    fn execute_cancellation(&mut self, caller: &AccountId) -> Promise {
-        self.cancelled = true;
+    /// Split amount by fee config and transfer each share, recording fees paid
+    fn transfer_with_fees(&mut self, amount: Balance) -> Promise {
+        let split = self.fee_split(amount);
+        self.protocol_fees_paid += split.protocol_fee;
+        self.integrator_fees_paid += split.integrator_fee;
 
-        // Return tokens to taker and safety deposit to caller
-        let token_transfer = Promise::new(self.immutables.token_id.clone()).function_call(
+        let mut transfer = self.ft_transfer(&self.immutables.maker, split.maker_amount);
+        if let Some(ref fees) = self.immutables.fee_config {
+            if split.protocol_fee > 0 {
+                transfer = transfer.and(self.ft_transfer(&fees.protocol_fee_recipient, split.protocol_fee));
+            }
+            if split.integrator_fee > 0 {
+                if let Some(ref recipient) = fees.integrator_fee_recipient {
+                    transfer = transfer.and(self.ft_transfer(recipient, split.integrator_fee));
+                }
+            }
+        }
+        transfer
+    }
+
+    fn fee_split(&self, amount: Balance) -> FeeSplit {
+        match self.immutables.fee_config {
+            Some(ref fees) => fees.split(amount),
+            None => FeeSplit { maker_amount: amount, protocol_fee: 0, integrator_fee: 0 },
+        }
+    }
+
+    fn ft_transfer(&self, receiver_id: &AccountId, amount: Balance) -> Promise {
+        Promise::new(self.immutables.token_id.clone()).function_call(
             "ft_transfer".to_string(),
             format!(r#"{{"receiver_id": "{}", "amount": "{}"}}"#, 
-                   self.immutables.taker, self.immutables.amount).into_bytes(),
+                   receiver_id, amount).into_bytes(),
             NearToken::from_yoctonear(1), // 1 yoctoNEAR for security
             Gas::from_tgas(30),
-        );
+        )
+    }
+
+    fn execute_cancellation(&mut self, caller: &AccountId) -> Promise {
+        self.cancelled = true;
+
+        // Return tokens to taker and safety deposit to caller
+        let token_transfer = self.ft_transfer(&self.immutables.taker, self.immutables.amount);
 
         let safety_deposit_transfer = Promise::new(caller.clone()).transfer(NearToken::from_yoctonear(self.immutables.safety_deposit));
 
@@ -443,7 +906,65 @@ impl EscrowDst {
 mod tests {
     use super::*;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, MockedBlockchain};
+    use near_sdk::testing_env;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const MAKER_KEY: [u8; 32] = [9u8; 32];
+
+    /// Implicit account of `MAKER_KEY`
+    fn maker() -> AccountId {
+        hex::encode(SigningKey::from_bytes(&MAKER_KEY).verifying_key().to_bytes()).parse().unwrap()
+    }
+
+    /// Router that initializes the test escrows
+    fn router() -> AccountId {
+        accounts(0)
+    }
+
+    /// Order intent for an escrow created with the test token, maker and timelocks
+    fn order_intent(
+        hashlock: [u8; 32],
+        amount: Balance,
+        total_parts: u64,
+        fee_config: Option<FeeConfig>,
+        hash_algorithm: HashAlgorithm,
+        nonce: u64,
+    ) -> OrderIntent {
+        OrderIntent {
+            domain: ORDER_INTENT_DOMAIN.to_string(),
+            escrow_id: env::current_account_id(),
+            router: router(),
+            hashlock,
+            hash_algorithm,
+            token_id: accounts(1),
+            amount,
+            maker: maker(),
+            timelocks: 0,
+            total_parts,
+            fee_config,
+            nonce,
+            guardian: None,
+        }
+    }
+
+    fn sign_intent(intent: &OrderIntent) -> MakerSignature {
+        let key = SigningKey::from_bytes(&MAKER_KEY);
+        MakerSignature::Ed25519 {
+            public_key: key.verifying_key().to_bytes().into(),
+            signature: hex::encode(key.sign(&intent.digest()).to_bytes()),
+        }
+    }
+
+    fn sign_order(
+        hashlock: [u8; 32],
+        amount: Balance,
+        total_parts: u64,
+        fee_config: Option<FeeConfig>,
+        hash_algorithm: HashAlgorithm,
+        nonce: u64,
+    ) -> MakerSignature {
+        sign_intent(&order_intent(hashlock, amount, total_parts, fee_config, hash_algorithm, nonce))
+    }
 
     #[test]
     fn test_escrow_initialization() {
@@ -454,13 +975,18 @@ mod tests {
 
         let hashlock = [1u8; 32];
         let escrow = EscrowDst::new(
-            hashlock,
+            hashlock.into(),
             accounts(1),
             1000u128,
-            accounts(2),
+            maker(),
             accounts(3),
             500u128,
             0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order(hashlock, 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
         );
 
         assert_eq!(escrow.immutables.hashlock, hashlock);
@@ -478,14 +1004,19 @@ mod tests {
 
         let merkle_root = [2u8; 32];
         let escrow = EscrowDst::new_with_partial_fills(
-            merkle_root,
+            merkle_root.into(),
             accounts(1),
             1000u128,
-            accounts(2),
+            maker(),
             accounts(3),
             500u128,
             0u64,
             4, // 4 parts
+            None,
+            None,
+            0,
+            None,
+            sign_order(merkle_root, 1000u128, 4, None, HashAlgorithm::Keccak256, 0),
         );
 
         assert_eq!(escrow.immutables.hashlock, merkle_root);
@@ -519,14 +1050,19 @@ mod tests {
 
         let merkle_root = [2u8; 32];
         let escrow = EscrowDst::new_with_partial_fills(
-            merkle_root,
+            merkle_root.into(),
             accounts(1),
             1000u128,
-            accounts(2),
+            maker(),
             accounts(3),
             500u128,
             0u64,
             4, // 4 parts (25% each)
+            None,
+            None,
+            0,
+            None,
+            sign_order(merkle_root, 1000u128, 4, None, HashAlgorithm::Keccak256, 0),
         );
 
         // Test partial fill amount calculations
@@ -535,4 +1071,496 @@ mod tests {
         assert_eq!(escrow.calculate_partial_amount(2), 750); // 75% of 1000
         assert_eq!(escrow.calculate_partial_amount(3), 1000); // 100% of 1000
     }
-}
\ No newline at end of file
+
+    fn fee_config(protocol_fee_bps: u16, integrator_fee_bps: u16) -> FeeConfig {
+        FeeConfig {
+            protocol_fee_bps,
+            protocol_fee_recipient: accounts(4),
+            integrator_fee_bps,
+            integrator_fee_recipient: Some(accounts(5)),
+        }
+    }
+
+    #[test]
+    fn test_fee_split_adds_up() {
+        let amounts = [0u128, 1, 7, 999, 1000, 123_456_789, u64::MAX as u128, u128::MAX];
+        let configs = [fee_config(0, 0), fee_config(30, 0), fee_config(25, 15), fee_config(333, 1), fee_config(5000, 5000)];
+
+        for config in configs.iter() {
+            for amount in amounts.iter() {
+                let split = config.split(*amount);
+                assert_eq!(split.maker_amount + split.protocol_fee + split.integrator_fee, *amount);
+            }
+        }
+
+        // Fees round down, remainder goes to maker
+        let split = fee_config(25, 15).split(1000);
+        assert_eq!(split, FeeSplit { maker_amount: 997, protocol_fee: 2, integrator_fee: 1 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Total fee exceeds 100%")]
+    fn test_fee_config_rejects_excessive_fees() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .build();
+        testing_env!(context);
+
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            Some(fee_config(9000, 1001)),
+            None,
+            0,
+            None,
+            sign_order([1u8; 32], 1000u128, 0, Some(fee_config(9000, 1001)), HashAlgorithm::Keccak256, 0),
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_records_fees() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(router())
+            .build();
+        testing_env!(context);
+
+        let mut escrow = EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            10_000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            Some(fee_config(30, 20)),
+            None,
+            0,
+            None,
+            sign_order([1u8; 32], 10_000u128, 0, Some(fee_config(30, 20)), HashAlgorithm::Keccak256, 0),
+        );
+
+        let _ = escrow.execute_withdrawal(Bytes32([7u8; 32]), &accounts(3));
+
+        let status = escrow.get_status();
+        assert!(status.withdrawn);
+        assert_eq!(status.remaining_amount, 0);
+        assert_eq!(status.protocol_fees_paid, 30);
+        assert_eq!(status.integrator_fees_paid, 20);
+    }
+
+    #[test]
+    fn test_partial_fill_fees_add_up() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(router())
+            .build();
+        testing_env!(context);
+
+        let mut escrow = EscrowDst::new_with_partial_fills(
+            [2u8; 32].into(),
+            accounts(1),
+            1001u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            4,
+            Some(fee_config(37, 11)),
+            None,
+            0,
+            None,
+            sign_order([2u8; 32], 1001u128, 4, Some(fee_config(37, 11)), HashAlgorithm::Keccak256, 0),
+        );
+
+        // Apply fee split to each chunk and check the total is preserved
+        let mut maker_total = 0u128;
+        for index in 0..4 {
+            let fill_amount = escrow.calculate_partial_amount(index) - escrow.filled_amount;
+            let split = escrow.fee_split(fill_amount);
+            assert_eq!(split.maker_amount + split.protocol_fee + split.integrator_fee, fill_amount);
+            maker_total += split.maker_amount;
+
+            let proof = MerkleProof { index, secret_hash: [0u8; 32], proof: vec![] };
+            let _ = escrow.execute_partial_withdrawal(Bytes32([index as u8; 32]), proof, &accounts(3));
+        }
+
+        let status = escrow.get_status();
+        assert!(status.withdrawn);
+        assert_eq!(status.filled_amount, 1001);
+        assert_eq!(maker_total + status.protocol_fees_paid + status.integrator_fees_paid, 1001);
+    }
+
+    #[test]
+    fn test_hash_algorithm_vectors() {
+        let context = VMContextBuilder::new().build();
+        testing_env!(context);
+
+        let vectors: [(&[u8], &str, &str); 2] = [
+            (
+                b"",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                b"abc",
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+        ];
+
+        for (input, keccak, sha) in vectors.iter() {
+            assert_eq!(hex::encode(HashAlgorithm::Keccak256.hash(input)), *keccak);
+            assert_eq!(hex::encode(HashAlgorithm::Sha256.hash(input)), *sha);
+        }
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Keccak256);
+    }
+
+    #[test]
+    fn test_withdraw_validation_uses_hash_algorithm() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(router())
+            .build();
+        testing_env!(context);
+
+        let secret = Bytes32([7u8; 32]);
+        let hashlock = HashAlgorithm::Sha256.hash(&secret.0);
+        let escrow = EscrowDst::new(
+            hashlock.into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            Some(HashAlgorithm::Sha256),
+            0,
+            None,
+            sign_order(hashlock, 1000u128, 0, None, HashAlgorithm::Sha256, 0),
+        );
+
+        assert_eq!(escrow.immutables.hash_algorithm, HashAlgorithm::Sha256);
+        escrow.validate_withdraw(&accounts(3), &secret);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid secret")]
+    fn test_withdraw_validation_rejects_wrong_algorithm() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(router())
+            .build();
+        testing_env!(context);
+
+        let secret = Bytes32([7u8; 32]);
+        let escrow = EscrowDst::new(
+            HashAlgorithm::Sha256.hash(&secret.0).into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order(HashAlgorithm::Sha256.hash(&secret.0), 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
+        );
+
+        escrow.validate_withdraw(&accounts(3), &secret);
+    }
+
+    #[test]
+    fn test_bytes32_parsing() {
+        let hex_secret = "0x0707070707070707070707070707070707070707070707070707070707070707";
+        assert_eq!(Bytes32::from_hex(hex_secret), Ok(Bytes32([7u8; 32])));
+        assert_eq!(Bytes32::from_hex(&hex_secret[2..]), Ok(Bytes32([7u8; 32])));
+
+        // Wrong length and non-hex input are rejected
+        assert!(Bytes32::from_hex("0x0707").is_err());
+        assert!(Bytes32::from_hex(&format!("{}07", hex_secret)).is_err());
+        assert!(Bytes32::from_hex(&"zz".repeat(32)).is_err());
+        assert!(Bytes32::from_slice(&[7u8; 31]).is_err());
+    }
+
+    #[test]
+    fn test_bytes32_json() {
+        let from_hex: Bytes32 = near_sdk::serde_json::from_str(
+            r#""0x0707070707070707070707070707070707070707070707070707070707070707""#,
+        )
+        .unwrap();
+        let from_array: Bytes32 = near_sdk::serde_json::from_str(&format!("{:?}", [7u8; 32])).unwrap();
+        assert_eq!(from_hex, from_array);
+        assert_eq!(
+            near_sdk::serde_json::to_string(&from_hex).unwrap(),
+            r#""0x0707070707070707070707070707070707070707070707070707070707070707""#
+        );
+
+        assert!(near_sdk::serde_json::from_str::<Bytes32>(r#""0x07""#).is_err());
+        assert!(near_sdk::serde_json::from_str::<Bytes32>(&format!("{:?}", [7u8; 33])).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid hashlock")]
+    fn test_zero_hashlock_rejected() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .build();
+        testing_env!(context);
+
+        EscrowDst::new(
+            [0u8; 32].into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order([0u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid maker signature")]
+    fn test_escrow_rejects_mismatched_amount() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .build();
+        testing_env!(context);
+
+        // Maker signed 1000, resolver tries to create escrow for 900
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            900u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid maker signature")]
+    fn test_escrow_rejects_mismatched_nonce() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .build();
+        testing_env!(context);
+
+        // Signature for nonce 1 does not cover nonce 2
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            2,
+            None,
+            sign_order([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 1),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Key does not belong to maker")]
+    fn test_escrow_rejects_key_of_other_account() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .build();
+        testing_env!(context);
+
+        // Resolver signs the maker's order with its own key
+        let mut intent = order_intent([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 0);
+        intent.maker = accounts(2);
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            accounts(2),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_intent(&intent),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid maker signature")]
+    fn test_escrow_rejects_initializer_other_than_router() {
+        // Deleting and recreating the escrow account must not allow a direct replay
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(3)).build());
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_maker_signature() {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .build();
+        testing_env!(context);
+
+        let key = k256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let public_key = key.verifying_key().to_encoded_point(false);
+        let eth_address = format!("0x{}", hex::encode(&env::keccak256_array(&public_key.as_bytes()[1..])[12..]));
+
+        // personal_sign over the intent digest, as produced by an Ethereum wallet
+        let mut intent = order_intent([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 5);
+        intent.maker = eth_address.parse().unwrap();
+        let mut message = b"\x19Ethereum Signed Message:\n32".to_vec();
+        message.extend_from_slice(&intent.digest());
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&env::keccak256_array(&message)).unwrap();
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+
+        let escrow = EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            intent.maker.clone(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            5,
+            None,
+            MakerSignature::Secp256k1 { eth_address: eth_address.clone(), signature: hex::encode(signature) },
+        );
+
+        assert_eq!(escrow.immutables.maker_signer, MakerSigner::Secp256k1 { eth_address });
+        assert_eq!(escrow.get_order_digest(), Bytes32(intent.digest()));
+    }
+
+    fn guarded_escrow() -> EscrowDst {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(router()).build());
+        let mut intent = order_intent([1u8; 32], 1000u128, 0, None, HashAlgorithm::Keccak256, 0);
+        intent.guardian = Some(accounts(4));
+        EscrowDst::new(
+            [1u8; 32].into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            Some(accounts(4)),
+            sign_intent(&intent),
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Method paused")]
+    fn test_paused_withdraw_rejected() {
+        let mut escrow = guarded_escrow();
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(4)).build());
+        escrow.pause(Some(vec![PausableMethod::Withdraw]));
+        assert!(escrow.is_paused(PausableMethod::Withdraw));
+        assert!(!escrow.is_paused(PausableMethod::PublicWithdraw));
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(3)).build());
+        let _ = escrow.withdraw(Bytes32([7u8; 32]));
+    }
+
+    #[test]
+    fn test_unpause_restores_methods() {
+        let mut escrow = guarded_escrow();
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(4)).build());
+        escrow.pause(None);
+        assert_eq!(escrow.get_status().paused_methods, PAUSABLE_METHODS.to_vec());
+        escrow.unpause(None);
+        assert!(escrow.get_status().paused_methods.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only guardian can pause")]
+    fn test_pause_requires_guardian() {
+        let mut escrow = guarded_escrow();
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(3)).build());
+        escrow.pause(None);
+    }
+
+    fn router_escrow() -> EscrowDst {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(router()).build());
+        EscrowDst::new(
+            HashAlgorithm::Keccak256.hash(&[7u8; 32]).into(),
+            accounts(1),
+            1000u128,
+            maker(),
+            accounts(3),
+            500u128,
+            0u64,
+            None,
+            None,
+            0,
+            None,
+            sign_order(HashAlgorithm::Keccak256.hash(&[7u8; 32]), 1000u128, 0, None, HashAlgorithm::Keccak256, 0),
+        )
+    }
+
+    #[test]
+    fn test_router_relays_withdraw_for_taker() {
+        let mut escrow = router_escrow();
+        let _ = escrow.withdraw_via_router(accounts(3), Bytes32([7u8; 32]));
+        assert!(escrow.is_withdrawn());
+
+        // Safety deposit is paid to the taker, not the router
+        let deposit_receivers: Vec<AccountId> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.actions.iter().any(|action| matches!(action, near_sdk::mock::MockAction::Transfer { .. })))
+            .map(|receipt| receipt.receiver_id)
+            .collect();
+        assert_eq!(deposit_receivers, vec![accounts(3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only router can relay")]
+    fn test_relay_rejected_from_other_accounts() {
+        let mut escrow = router_escrow();
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        let _ = escrow.withdraw_via_router(accounts(3), Bytes32([7u8; 32]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only taker can cancel")]
+    fn test_router_relays_only_for_taker() {
+        let mut escrow = router_escrow();
+        let _ = escrow.cancel_via_router(accounts(2));
+    }
+}
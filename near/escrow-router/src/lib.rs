@@ -0,0 +1,558 @@
+// Router that initializes and batch-settles fusion-near-escrow (EscrowDst) accounts
+//
+// An escrow is a standalone account, so the escrow alone cannot tell whether a signed
+// order was already used by an escrow that was deleted and created again. The maker
+// signs this router into the order intent, the escrow only accepts its initialization
+// from the signed router, and the router accepts each `(maker, nonce)` pair once per
+// escrow. The escrow account is part of the key because only the escrow checks the
+// signature: a pair forwarded to any other account cannot use up the maker's nonce.
+// Escrows also accept withdrawals and cancellations relayed by their router on behalf
+// of the router's caller, which lets a taker settle many escrows in one transaction.
+// The protocol guardian can pause `init_escrow` to stop new escrows; settlement through
+// `batch_settle` is never paused, so funds in existing escrows stay recoverable.
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::store::LookupSet;
+use near_sdk::{
+    env, ext_contract, near_bindgen, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault, Promise, PromiseError,
+    PromiseOrValue,
+};
+
+const GAS_FOR_ESCROW_INIT: Gas = Gas::from_tgas(30);
+const GAS_FOR_INIT_CALLBACK: Gas = Gas::from_tgas(10);
+
+// Gas sent with each escrow call of `batch_settle`: a withdrawal with fees makes
+// three 30 Tgas `ft_transfer`s, a cancellation one, plus the escrow's own work
+const GAS_FOR_BATCH_WITHDRAW: Gas = Gas::from_tgas(100);
+const GAS_FOR_BATCH_CANCEL: Gas = Gas::from_tgas(40);
+const GAS_FOR_BATCH_CALLBACK: Gas = Gas::from_tgas(15);
+// Kept back for the rest of `batch_settle` once the escrow calls are chosen
+const GAS_FOR_BATCH_EXECUTION: Gas = Gas::from_tgas(10);
+
+// Escrow settlements return no data; the limit only bounds what the callback reads
+const MAX_ESCROW_RESULT_LEN: usize = 1024;
+
+#[ext_contract(ext_escrow)]
+#[allow(dead_code)]
+trait EscrowDst {
+    fn withdraw_via_router(&mut self, taker: AccountId, secret: String);
+    fn cancel_via_router(&mut self, taker: AccountId);
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(crate = "near_sdk::borsh")]
+enum StorageKey {
+    UsedNonces,
+}
+
+// Escrow initializer called by `init_escrow`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum EscrowInit {
+    New,
+    NewWithPartialFills,
+}
+
+impl EscrowInit {
+    fn method_name(&self) -> &'static str {
+        match self {
+            EscrowInit::New => "new",
+            EscrowInit::NewWithPartialFills => "new_with_partial_fills",
+        }
+    }
+}
+
+// One escrow settlement of a `batch_settle` call
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde", tag = "action", rename_all = "snake_case")]
+pub enum BatchAction {
+    Withdraw { escrow_id: AccountId, secret: String }, // Secret as hex, passed to the escrow unchanged
+    Cancel { escrow_id: AccountId },
+}
+
+impl BatchAction {
+    fn escrow_id(&self) -> &AccountId {
+        match self {
+            BatchAction::Withdraw { escrow_id, .. } | BatchAction::Cancel { escrow_id } => escrow_id,
+        }
+    }
+
+    fn gas(&self) -> Gas {
+        match self {
+            BatchAction::Withdraw { .. } => GAS_FOR_BATCH_WITHDRAW,
+            BatchAction::Cancel { .. } => GAS_FOR_BATCH_CANCEL,
+        }
+    }
+
+    fn call(&self, taker: AccountId) -> Promise {
+        let escrow = ext_escrow::ext(self.escrow_id().clone()).with_static_gas(self.gas());
+        match self {
+            BatchAction::Withdraw { secret, .. } => escrow.withdraw_via_router(taker, secret.clone()),
+            BatchAction::Cancel { .. } => escrow.cancel_via_router(taker),
+        }
+    }
+}
+
+// Per-item outcome of `batch_settle`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchItemResult {
+    pub escrow_id: AccountId,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct EscrowRouter {
+    used_nonces: LookupSet<(AccountId, u64, AccountId)>, // (maker, nonce, escrow_id) of every initialized escrow
+    guardian: AccountId,                                 // Protocol guardian, can pause escrow creation
+    creation_paused: bool,
+}
+
+#[near_bindgen]
+impl EscrowRouter {
+    #[init]
+    pub fn new(guardian: AccountId) -> Self {
+        Self { used_nonces: LookupSet::new(StorageKey::UsedNonces), guardian, creation_paused: false }
+    }
+
+    /// Initialize a deployed escrow, passing `args` to its `init` method unchanged.
+    /// Rejects a `(maker, nonce)` pair that already initialized this escrow. The attached
+    /// deposit pays for the nonce record and the rest is refunded; if the escrow rejects
+    /// the initialization, such as for an invalid maker signature, the nonce is released
+    /// and its storage refunded.
+    #[payable]
+    pub fn init_escrow(&mut self, escrow_id: AccountId, init: EscrowInit, args: Value) -> Promise {
+        assert!(!self.creation_paused, "Escrow creation paused");
+        let maker: AccountId = args
+            .get("maker")
+            .and_then(Value::as_str)
+            .and_then(|maker| maker.parse().ok())
+            .unwrap_or_else(|| env::panic_str("Missing maker"));
+        let nonce = args.get("nonce").and_then(Value::as_u64).unwrap_or_else(|| env::panic_str("Missing nonce"));
+
+        let initial_storage = env::storage_usage();
+        assert!(self.used_nonces.insert((maker.clone(), nonce, escrow_id.clone())), "Nonce already used");
+        let storage_cost = env::storage_byte_cost().saturating_mul((env::storage_usage() - initial_storage).into());
+        let refund = env::attached_deposit()
+            .checked_sub(storage_cost)
+            .unwrap_or_else(|| env::panic_str("Attached deposit does not cover nonce storage"));
+
+        let payer = env::predecessor_account_id();
+        if !refund.is_zero() {
+            let _ = Promise::new(payer.clone()).transfer(refund);
+        }
+
+        Promise::new(escrow_id.clone())
+            .function_call(
+                init.method_name().to_string(),
+                args.to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_ESCROW_INIT,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_INIT_CALLBACK)
+                    .on_escrow_initialized(maker, nonce, escrow_id, payer, storage_cost),
+            )
+    }
+
+    /// Release the nonce and refund its storage if the escrow rejected the initialization
+    #[private]
+    pub fn on_escrow_initialized(
+        &mut self,
+        maker: AccountId,
+        nonce: u64,
+        escrow_id: AccountId,
+        payer: AccountId,
+        storage_cost: NearToken,
+    ) -> bool {
+        match env::promise_result_checked(0, 0) {
+            Ok(_) => true,
+            Err(PromiseError::Failed) => {
+                self.used_nonces.remove(&(maker, nonce, escrow_id));
+                let _ = Promise::new(payer).transfer(storage_cost);
+                false
+            }
+            // Result too long: the initializer ran, so the nonce stays used
+            Err(_) => true,
+        }
+    }
+
+    /// Withdraw from or cancel many escrows in one transaction on behalf of the caller.
+    /// Each escrow checks that it was initialized by this router and that the caller is
+    /// its taker. Every escrow call is a separate receipt, so one failing item does not
+    /// affect the others. Items the remaining gas cannot cover are skipped and reported.
+    pub fn batch_settle(&mut self, items: Vec<BatchAction>) -> PromiseOrValue<Vec<BatchItemResult>> {
+        let taker = env::predecessor_account_id();
+        let mut calls: Option<Promise> = None;
+        let mut sent = Vec::with_capacity(items.len());
+        // Scheduled calls only claim their static gas when the receipts are created
+        let mut reserved = GAS_FOR_BATCH_CALLBACK.saturating_add(GAS_FOR_BATCH_EXECUTION);
+        for item in &items {
+            let available = env::prepaid_gas().saturating_sub(env::used_gas());
+            let fits = reserved.saturating_add(item.gas()) <= available;
+            if fits {
+                reserved = reserved.saturating_add(item.gas());
+                let call = item.call(taker.clone());
+                calls = Some(match calls {
+                    Some(calls) => calls.and(call),
+                    None => call,
+                });
+            }
+            sent.push(fits);
+        }
+
+        let escrow_ids = items.iter().map(|item| item.escrow_id().clone()).collect();
+        match calls {
+            Some(calls) => PromiseOrValue::Promise(calls.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_BATCH_CALLBACK)
+                    .on_batch_settled(escrow_ids, sent),
+            )),
+            None => PromiseOrValue::Value(Self::on_batch_settled(escrow_ids, sent)),
+        }
+    }
+
+    /// Match the escrow call results to the batch items, in order
+    #[private]
+    pub fn on_batch_settled(escrow_ids: Vec<AccountId>, sent: Vec<bool>) -> Vec<BatchItemResult> {
+        let mut result_index = 0;
+        escrow_ids
+            .into_iter()
+            .zip(sent)
+            .map(|(escrow_id, sent)| {
+                let error = if !sent {
+                    Some("Insufficient gas for batch item")
+                } else {
+                    let result = env::promise_result_checked(result_index, MAX_ESCROW_RESULT_LEN);
+                    result_index += 1;
+                    match result {
+                        Ok(_) => None,
+                        Err(PromiseError::Failed) => Some("Escrow call failed"),
+                        Err(_) => Some("Escrow result too long"),
+                    }
+                };
+                BatchItemResult {
+                    escrow_id,
+                    success: error.is_none(),
+                    error: error.map(str::to_string),
+                }
+            })
+            .collect()
+    }
+
+    /// Stop `init_escrow` from creating escrows. Guardian only.
+    pub fn pause_creation(&mut self) {
+        self.assert_guardian();
+        if !self.creation_paused {
+            self.creation_paused = true;
+            Self::emit_pause_event("paused");
+        }
+    }
+
+    /// Resume escrow creation. Guardian only.
+    pub fn unpause_creation(&mut self) {
+        self.assert_guardian();
+        if self.creation_paused {
+            self.creation_paused = false;
+            Self::emit_pause_event("unpaused");
+        }
+    }
+
+    pub fn is_nonce_used(&self, maker: AccountId, nonce: u64, escrow_id: AccountId) -> bool {
+        self.used_nonces.contains(&(maker, nonce, escrow_id))
+    }
+
+    pub fn is_creation_paused(&self) -> bool {
+        self.creation_paused
+    }
+
+    pub fn get_guardian(&self) -> &AccountId {
+        &self.guardian
+    }
+}
+
+impl EscrowRouter {
+    fn assert_guardian(&self) {
+        assert_eq!(env::predecessor_account_id(), self.guardian, "Only guardian can pause");
+    }
+
+    // NEP-297 event for pause state changes
+    fn emit_pause_event(event: &str) {
+        let event = json!({
+            "standard": "escrow-router",
+            "version": "1.0.0",
+            "event": event,
+            "data": { "method": "init_escrow", "by": env::predecessor_account_id() },
+        });
+        env::log_str(&format!("EVENT_JSON:{}", event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+
+    fn guardian() -> AccountId {
+        accounts(0)
+    }
+
+    fn resolver() -> AccountId {
+        accounts(2)
+    }
+
+    fn escrow_args(nonce: u64) -> Value {
+        json!({ "maker": accounts(1), "nonce": nonce, "amount": "1000" })
+    }
+
+    fn escrow() -> AccountId {
+        accounts(3)
+    }
+
+    fn init(router: &mut EscrowRouter, escrow_id: AccountId, nonce: u64) -> Promise {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(resolver())
+            .attached_deposit(NearToken::from_millinear(10))
+            .build());
+        router.init_escrow(escrow_id, EscrowInit::New, escrow_args(nonce))
+    }
+
+    fn init_result(result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_init_escrow_records_nonce() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut router = EscrowRouter::new(guardian());
+        let _ = init(&mut router, escrow(), 7);
+
+        assert!(router.is_nonce_used(accounts(1), 7, escrow()));
+        assert!(!router.is_nonce_used(accounts(1), 8, escrow()));
+        let forwarded = get_created_receipts().into_iter().any(|receipt| {
+            receipt.receiver_id == escrow()
+                && receipt.actions.iter().any(|action| {
+                    matches!(action, MockAction::FunctionCallWeight { method_name, args, .. }
+                        if method_name == b"new" && *args == escrow_args(7).to_string().into_bytes())
+                })
+        });
+        assert!(forwarded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Nonce already used")]
+    fn test_init_escrow_rejects_reused_nonce() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut router = EscrowRouter::new(guardian());
+        let _ = init(&mut router, escrow(), 7);
+        let _ = init(&mut router, escrow(), 7);
+    }
+
+    #[test]
+    fn test_nonce_sent_to_other_account_does_not_block_escrow() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut router = EscrowRouter::new(guardian());
+        // Forwarded to an account of the caller's choosing, which need not check the signature
+        let _ = init(&mut router, accounts(4), 7);
+        let _ = init(&mut router, escrow(), 7);
+        assert!(router.is_nonce_used(accounts(1), 7, escrow()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit does not cover nonce storage")]
+    fn test_init_escrow_requires_storage_deposit() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(resolver()).build());
+        let mut router = EscrowRouter::new(guardian());
+        let _ = router.init_escrow(escrow(), EscrowInit::New, escrow_args(7));
+    }
+
+    #[test]
+    fn test_failed_init_releases_nonce() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut router = EscrowRouter::new(guardian());
+        let _ = init(&mut router, escrow(), 7);
+
+        init_result(PromiseResult::Failed);
+        let cost = NearToken::from_yoctonear(1000);
+        assert!(!router.on_escrow_initialized(accounts(1), 7, escrow(), resolver(), cost));
+        assert!(!router.is_nonce_used(accounts(1), 7, escrow()));
+    }
+
+    #[test]
+    fn test_successful_init_keeps_nonce() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut router = EscrowRouter::new(guardian());
+        let _ = init(&mut router, escrow(), 7);
+
+        init_result(PromiseResult::Successful(vec![]));
+        let cost = NearToken::from_yoctonear(1000);
+        assert!(router.on_escrow_initialized(accounts(1), 7, escrow(), resolver(), cost));
+        assert!(router.is_nonce_used(accounts(1), 7, escrow()));
+    }
+
+    fn paused_router() -> EscrowRouter {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(guardian()).build());
+        let mut router = EscrowRouter::new(guardian());
+        router.pause_creation();
+        router
+    }
+
+    #[test]
+    #[should_panic(expected = "Escrow creation paused")]
+    fn test_paused_creation_rejects_init() {
+        let mut router = paused_router();
+        assert!(get_logs()[0].contains(r#""event":"paused""#));
+        let _ = init(&mut router, escrow(), 7);
+    }
+
+    #[test]
+    fn test_unpause_creation_restores_init() {
+        let mut router = paused_router();
+        router.unpause_creation();
+        assert!(!router.is_creation_paused());
+        assert!(get_logs()[1].contains(r#""event":"unpaused""#));
+        let _ = init(&mut router, escrow(), 7);
+        assert!(router.is_nonce_used(accounts(1), 7, escrow()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only guardian can pause")]
+    fn test_pause_creation_requires_guardian() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(resolver()).build());
+        let mut router = EscrowRouter::new(guardian());
+        router.pause_creation();
+    }
+
+    #[test]
+    fn test_batch_settle_relays_for_caller() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(resolver()).build());
+        let mut router = EscrowRouter::new(guardian());
+        let items = vec![
+            BatchAction::Withdraw { escrow_id: accounts(1), secret: "07".repeat(32) },
+            BatchAction::Cancel { escrow_id: accounts(3) },
+        ];
+        let _ = router.batch_settle(items);
+
+        let calls: Vec<(AccountId, Vec<u8>, Value)> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id;
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::FunctionCallWeight { method_name, args, .. } if method_name != b"on_batch_settled" => {
+                        Some((receiver_id.clone(), method_name, near_sdk::serde_json::from_slice(&args).unwrap()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    accounts(1),
+                    b"withdraw_via_router".to_vec(),
+                    json!({ "taker": resolver(), "secret": "07".repeat(32) }),
+                ),
+                (accounts(3), b"cancel_via_router".to_vec(), json!({ "taker": resolver() })),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_settle_skips_items_without_gas() {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(resolver())
+            .prepaid_gas(Gas::from_tgas(50))
+            .build());
+        let mut router = EscrowRouter::new(guardian());
+        let items = vec![
+            BatchAction::Withdraw { escrow_id: accounts(1), secret: "07".repeat(32) },
+            BatchAction::Cancel { escrow_id: accounts(3) },
+        ];
+        let PromiseOrValue::Value(results) = router.batch_settle(items) else {
+            panic!("Expected no escrow calls");
+        };
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.error.as_deref() == Some("Insufficient gas for batch item")));
+    }
+
+    #[test]
+    fn test_batch_settle_skips_items_past_prepaid_gas() {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(resolver())
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let mut router = EscrowRouter::new(guardian());
+        let items = (1..=4)
+            .map(|n| BatchAction::Withdraw { escrow_id: accounts(n), secret: "07".repeat(32) })
+            .collect();
+        let _ = router.batch_settle(items);
+
+        let callback_args: Value = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .find_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"on_batch_settled" => {
+                    Some(near_sdk::serde_json::from_slice(&args).unwrap())
+                }
+                _ => None,
+            })
+            .unwrap();
+        // Two withdrawals and the callback fit in 300 Tgas, the third would not
+        assert_eq!(callback_args["sent"], json!([true, true, false, false]));
+    }
+
+    #[test]
+    fn test_on_batch_settled_maps_results() {
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Failed,
+                PromiseResult::Successful(vec![0u8; MAX_ESCROW_RESULT_LEN + 1]),
+            ],
+        );
+        let results = EscrowRouter::on_batch_settled(
+            vec![accounts(1), accounts(2), accounts(3), accounts(4)],
+            vec![true, false, true, true],
+        );
+        assert_eq!(
+            results,
+            vec![
+                BatchItemResult { escrow_id: accounts(1), success: true, error: None },
+                BatchItemResult {
+                    escrow_id: accounts(2),
+                    success: false,
+                    error: Some("Insufficient gas for batch item".to_string()),
+                },
+                BatchItemResult {
+                    escrow_id: accounts(3),
+                    success: false,
+                    error: Some("Escrow call failed".to_string()),
+                },
+                BatchItemResult {
+                    escrow_id: accounts(4),
+                    success: false,
+                    error: Some("Escrow result too long".to_string()),
+                },
+            ]
+        );
+    }
+}